@@ -0,0 +1,329 @@
+use anyhow::Context;
+use clap::Parser;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use woopra_core::data_collection::{Dict, EdgeeRequest, Event, HttpMethod};
+use woopra_core::{DecodedRequest, Settings};
+
+// runs Edgee events through the same mapping as the component and prints the resulting requests
+#[derive(Parser, Clone)]
+#[command(version, about)]
+struct Args {
+    /// Component setting, as key=value (can be repeated, overrides the settings file)
+    #[arg(short, long = "setting", value_name = "KEY=VALUE", value_parser = parse_setting)]
+    settings: Vec<(String, String)>,
+
+    /// JSON file with an object of component settings
+    #[arg(long, value_name = "FILE")]
+    settings_file: Option<PathBuf>,
+
+    /// Decode raw Woopra request URLs (one per line, e.g. from proxy logs) instead of mapping events
+    #[arg(long)]
+    decode: bool,
+
+    /// Files with Edgee events as a JSON array, a JSON object or JSON lines ("-" for stdin)
+    #[arg(value_name = "FILE")]
+    inputs: Vec<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// returns false if any event could not be mapped
+fn run(args: Args) -> anyhow::Result<bool> {
+    let mut inputs = args.inputs.clone();
+    if inputs.is_empty() {
+        inputs.push(PathBuf::from("-"));
+    }
+    if args.decode {
+        return decode(&inputs);
+    }
+
+    let settings = Settings::new(read_settings(&args)?)?;
+
+    let mut success = true;
+    let mut index = 0;
+    for input in inputs.iter() {
+        let content = read_input(input)?;
+        for event in parse_events(&content).with_context(|| format!("{}", input.display()))? {
+            index += 1;
+            println!("# event {index} ({:?})", event.event_type);
+            match woopra_core::build_request(&event, &settings) {
+                Ok(request) => print_request(&request),
+                Err(err) => {
+                    println!("error: {err:#}");
+                    success = false;
+                }
+            }
+            println!();
+        }
+    }
+
+    Ok(success)
+}
+
+// returns false if any line is not a Woopra request URL
+fn decode(inputs: &[PathBuf]) -> anyhow::Result<bool> {
+    let mut success = true;
+    for input in inputs.iter() {
+        let content = read_input(input)?;
+        for url in content.lines().filter(|line| !line.trim().is_empty()) {
+            println!("{}", url.trim());
+            match woopra_core::decode_url(url) {
+                Ok(decoded) => print_decoded(&decoded),
+                Err(err) => {
+                    println!("error: {err:#}");
+                    success = false;
+                }
+            }
+            println!();
+        }
+    }
+
+    Ok(success)
+}
+
+fn parse_setting(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("invalid setting \"{value}\", expected key=value"))
+}
+
+// the settings file is read first so that --setting can override it
+fn read_settings(args: &Args) -> anyhow::Result<Dict> {
+    let mut settings: BTreeMap<String, String> = BTreeMap::new();
+    if let Some(path) = &args.settings_file {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let values: BTreeMap<String, serde_json::Value> = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid settings file {}", path.display()))?;
+        for (key, value) in values {
+            let value = match value {
+                serde_json::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            settings.insert(key, value);
+        }
+    }
+    settings.extend(args.settings.iter().cloned());
+
+    Ok(settings.into_iter().collect())
+}
+
+fn read_input(path: &PathBuf) -> anyhow::Result<String> {
+    if path.as_os_str() == "-" {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .context("Failed to read stdin")?;
+        return Ok(content);
+    }
+    fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+// accepts a JSON array of events, or one or more JSON objects (including JSON lines)
+fn parse_events(content: &str) -> anyhow::Result<Vec<Event>> {
+    if content.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(content)?);
+    }
+    serde_json::Deserializer::from_str(content)
+        .into_iter::<Event>()
+        .map(|event| event.map_err(anyhow::Error::from))
+        .collect()
+}
+
+fn print_request(request: &EdgeeRequest) {
+    let method = match request.method {
+        HttpMethod::Head => "HEAD",
+        HttpMethod::Get => "GET",
+        HttpMethod::Put => "PUT",
+        HttpMethod::Post => "POST",
+        HttpMethod::Delete => "DELETE",
+    };
+    println!("{method} {}", request.url);
+    for (name, value) in request.headers.iter() {
+        println!("{name}: {value}");
+    }
+    if request.forward_client_headers {
+        println!("(client headers forwarded)");
+    }
+
+    if !request.body.is_empty() {
+        println!();
+        println!("{}", request.body.trim_end());
+    }
+
+    // the Woopra URL is the request URL, or each line of a mirror mode fan-out body
+    if let Ok(decoded) = woopra_core::decode_url(&request.url) {
+        print_decoded(&decoded);
+    } else {
+        for decoded in request.body.lines().flat_map(woopra_core::decode_url) {
+            println!();
+            println!("# project {}", decoded.project().unwrap_or_default());
+            print_decoded(&decoded);
+        }
+    }
+}
+
+// one table per scope, with the prefixes removed
+fn print_decoded(decoded: &DecodedRequest) {
+    let sections = [
+        ("request", &decoded.params),
+        ("visitor (cv_)", &decoded.visitor),
+        ("event (ce_)", &decoded.event),
+        ("session (cs_)", &decoded.session),
+    ];
+    let width = sections
+        .iter()
+        .flat_map(|(_, fields)| fields.keys())
+        .map(|name| name.len())
+        .max()
+        .unwrap_or_default();
+
+    for (title, fields) in sections.iter().filter(|(_, fields)| !fields.is_empty()) {
+        println!();
+        println!("  [{title}]");
+        for (name, value) in fields.iter() {
+            println!("  {name:<width$}  {value}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use woopra_core::data_collection::{Data, EventType};
+
+    const PAGE_EVENT: &str = r#"{"uuid": "e1", "type": "page", "data": {"title": "Home"}}"#;
+    const TRACK_EVENT: &str = r#"{"uuid": "e2", "type": "track", "data": {"name": "Signup", "properties": {"plan": "pro"}}}"#;
+
+    fn args(settings_file: Option<PathBuf>, settings: &[(&str, &str)]) -> Args {
+        Args {
+            settings: settings
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            settings_file,
+            decode: false,
+            inputs: vec![],
+        }
+    }
+
+    // writes a file in a temporary directory, removed when the directory is dropped
+    fn temp_file(dir: &TempDir, name: &str, content: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn events_are_parsed_from_arrays_objects_and_json_lines() {
+        let array = parse_events(&format!("[{PAGE_EVENT}, {TRACK_EVENT}]")).unwrap();
+        let lines = parse_events(&format!("{PAGE_EVENT}\n{TRACK_EVENT}\n")).unwrap();
+        assert_eq!(array, lines);
+        assert_eq!(array.len(), 2);
+        assert_eq!(array[0].event_type, EventType::Page);
+        assert_eq!(array[1].uuid, "e2");
+        let Data::Track(ref data) = array[1].data else {
+            panic!("expected track data");
+        };
+        assert_eq!(data.name, "Signup");
+        assert_eq!(
+            data.properties,
+            vec![("plan".to_string(), "pro".to_string())]
+        );
+
+        let single = parse_events(PAGE_EVENT).unwrap();
+        assert_eq!(single, array[..1].to_vec());
+        assert_eq!(parse_events("").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn malformed_events_are_errors() {
+        assert!(parse_events(r#"{"type": "page", "data": "#).is_err());
+        assert!(parse_events(&format!("[{PAGE_EVENT},]")).is_err());
+        assert!(parse_events(&format!("{PAGE_EVENT}\nnot json")).is_err());
+        assert!(parse_events(r#"{"type": "unknown", "data": {}}"#).is_err());
+    }
+
+    #[test]
+    fn settings_are_read_from_file_and_overridden_by_flags() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_file(
+            &dir,
+            "settings.json",
+            r#"{"project_name": "example.com", "sample_rate": 0.5, "debug_mode": false}"#,
+        );
+        let settings = read_settings(&args(Some(path), &[("sample_rate", "1")])).unwrap();
+        assert_eq!(
+            settings,
+            vec![
+                ("debug_mode".to_string(), "false".to_string()),
+                ("project_name".to_string(), "example.com".to_string()),
+                ("sample_rate".to_string(), "1".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_setting("app_name=brand=a"),
+            Ok(("app_name".to_string(), "brand=a".to_string()))
+        );
+        assert!(parse_setting("app_name").is_err());
+    }
+
+    #[test]
+    fn malformed_settings_are_errors() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_file(&dir, "malformed.json", r#"{"project_name": "#);
+        let err = read_settings(&args(Some(path.clone()), &[])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Invalid settings file {}", path.display())
+        );
+
+        let missing = path.with_file_name("missing.json");
+        assert!(read_settings(&args(Some(missing), &[])).is_err());
+
+        // settings are validated before any event is read
+        let mut invalid = args(
+            None,
+            &[("project_name", "example.com"), ("sample_rate", "2")],
+        );
+        invalid.inputs = vec![temp_file(&dir, "events.json", PAGE_EVENT)];
+        assert!(run(invalid).is_err());
+    }
+
+    #[test]
+    fn run_reports_events_that_cannot_be_mapped() {
+        let dir = TempDir::new().unwrap();
+        let mut valid = args(None, &[("project_name", "example.com")]);
+        valid.inputs = vec![temp_file(&dir, "valid.json", PAGE_EVENT)];
+        assert!(run(valid).unwrap());
+
+        let mut disabled = args(
+            None,
+            &[
+                ("project_name", "example.com"),
+                ("edgee_page_event_enabled", "false"),
+            ],
+        );
+        disabled.inputs = vec![temp_file(&dir, "disabled.json", PAGE_EVENT)];
+        assert!(!run(disabled).unwrap());
+
+        let mut malformed = args(None, &[("project_name", "example.com")]);
+        malformed.inputs = vec![temp_file(&dir, "malformed_events.json", "{")];
+        assert!(run(malformed).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+// fuzzes the mapping with arbitrary events and checks the same invariants as the property-based tests
+#![no_main]
+
+#[path = "../../tests/invariants/mod.rs"]
+mod invariants;
+
+use arbitrary::Arbitrary;
+use invariants::Filter;
+use libfuzzer_sys::fuzz_target;
+use woopra_core::data_collection::{
+    Context, Data, Event, EventType, PageData, TrackData, UserData,
+};
+use woopra_core::{CollisionPolicy, Settings};
+
+#[derive(Arbitrary, Debug)]
+enum FuzzData {
+    Page {
+        title: String,
+        properties: Vec<(String, String)>,
+    },
+    Track {
+        name: String,
+        properties: Vec<(String, String)>,
+    },
+    User {
+        user_id: String,
+        anonymous_id: String,
+        properties: Vec<(String, String)>,
+    },
+}
+
+#[derive(Arbitrary, Debug)]
+struct FuzzEvent {
+    data: FuzzData,
+    uuid: String,
+    timestamp: i64,
+    user_id: String,
+    anonymous_id: String,
+    user_properties: Vec<(String, String)>,
+    page_title: String,
+    page_url: String,
+    page_properties: Vec<(String, String)>,
+    country_code: String,
+    filter: u8,
+    policy: u8,
+}
+
+impl From<FuzzEvent> for Event {
+    fn from(fuzz: FuzzEvent) -> Self {
+        let (event_type, data) = match fuzz.data {
+            FuzzData::Page { title, properties } => (
+                EventType::Page,
+                Data::Page(PageData {
+                    title,
+                    properties,
+                    ..PageData::default()
+                }),
+            ),
+            FuzzData::Track { name, properties } => (
+                EventType::Track,
+                Data::Track(TrackData {
+                    name,
+                    properties,
+                    products: vec![],
+                }),
+            ),
+            FuzzData::User {
+                user_id,
+                anonymous_id,
+                properties,
+            } => (
+                EventType::User,
+                Data::User(UserData {
+                    user_id,
+                    anonymous_id,
+                    edgee_id: String::new(),
+                    properties,
+                }),
+            ),
+        };
+
+        let mut context = Context::default();
+        context.user.user_id = fuzz.user_id;
+        context.user.anonymous_id = fuzz.anonymous_id;
+        context.user.properties = fuzz.user_properties;
+        context.page.title = fuzz.page_title;
+        context.page.url = fuzz.page_url;
+        context.page.properties = fuzz.page_properties;
+        context.client.country_code = fuzz.country_code;
+
+        Event {
+            uuid: fuzz.uuid,
+            timestamp: fuzz.timestamp,
+            timestamp_millis: fuzz.timestamp.saturating_mul(1000),
+            timestamp_micros: fuzz.timestamp.saturating_mul(1_000_000),
+            event_type,
+            data,
+            context,
+            consent: None,
+        }
+    }
+}
+
+fuzz_target!(|fuzz: FuzzEvent| {
+    let filter = match fuzz.filter % 3 {
+        0 => Filter::None,
+        1 => Filter::Redact,
+        _ => Filter::Drop,
+    };
+    let (policy, policy_setting) = match fuzz.policy % 3 {
+        0 => (CollisionPolicy::ExplicitPrefixWins, "explicit_prefix_wins"),
+        1 => (CollisionPolicy::FirstWins, "first_wins"),
+        _ => (CollisionPolicy::Rename, "rename"),
+    };
+    let mut settings_dict = vec![
+        ("project_name".to_string(), "example.com".to_string()),
+        (
+            "property_collision_policy".to_string(),
+            policy_setting.to_string(),
+        ),
+    ];
+    settings_dict.extend(filter.settings());
+    let settings = Settings::new(settings_dict).unwrap();
+
+    let event = Event::from(fuzz);
+    match woopra_core::build_request(&event, &settings) {
+        Ok(request) => {
+            if let Err(err) = invariants::check(&event, &request, filter, policy) {
+                panic!("{err}");
+            }
+        }
+        // the only event the mapping refuses without event controls or sampling
+        Err(err) => assert_eq!(err.to_string(), "Track is not set"),
+    }
+});
@@ -0,0 +1,259 @@
+// host-independent copy of the Edgee data collection model (see the `edgee:components` WIT),
+// so that the mapping can be used and tested without the WIT bindings
+//
+// events can also be deserialized from Edgee's JSON representation, where properties are objects
+// and the "type" field tells which kind of data the event carries
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::fmt;
+
+pub type Dict = Vec<(String, String)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventType {
+    Page,
+    Track,
+    User,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Consent {
+    Pending,
+    Granted,
+    Denied,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "JsonEvent")]
+pub struct Event {
+    pub uuid: String,
+    pub timestamp: i64,
+    pub timestamp_millis: i64,
+    pub timestamp_micros: i64,
+    pub event_type: EventType,
+    pub data: Data,
+    pub context: Context,
+    pub consent: Option<Consent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+pub enum Data {
+    Page(PageData),
+    Track(TrackData),
+    User(UserData),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PageData {
+    pub name: String,
+    pub category: String,
+    pub keywords: Vec<String>,
+    pub title: String,
+    pub url: String,
+    pub path: String,
+    pub search: String,
+    pub referrer: String,
+    #[serde(deserialize_with = "deserialize_dict")]
+    pub properties: Dict,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct UserData {
+    pub user_id: String,
+    pub anonymous_id: String,
+    pub edgee_id: String,
+    #[serde(deserialize_with = "deserialize_dict")]
+    pub properties: Dict,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TrackData {
+    pub name: String,
+    #[serde(deserialize_with = "deserialize_dict")]
+    pub properties: Dict,
+    #[serde(deserialize_with = "deserialize_dicts")]
+    pub products: Vec<Dict>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Context {
+    pub page: PageData,
+    pub user: UserData,
+    pub client: Client,
+    pub campaign: Campaign,
+    pub session: Session,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Client {
+    pub ip: String,
+    pub locale: String,
+    pub timezone: String,
+    pub user_agent: String,
+    pub user_agent_architecture: String,
+    pub user_agent_bitness: String,
+    pub user_agent_full_version_list: String,
+    pub user_agent_version_list: String,
+    pub user_agent_mobile: String,
+    pub user_agent_model: String,
+    pub os_name: String,
+    pub os_version: String,
+    pub screen_width: i32,
+    pub screen_height: i32,
+    pub screen_density: f32,
+    pub continent: String,
+    pub country_code: String,
+    pub country_name: String,
+    pub region: String,
+    pub city: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Campaign {
+    pub name: String,
+    pub source: String,
+    pub medium: String,
+    pub term: String,
+    pub content: String,
+    pub creative_format: String,
+    pub marketing_tactic: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub session_id: String,
+    pub previous_session_id: String,
+    pub session_count: u32,
+    pub session_start: bool,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    Head,
+    Get,
+    Put,
+    Post,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgeeRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Dict,
+    pub forward_client_headers: bool,
+    pub body: String,
+}
+
+// the JSON representation of an event, the event type is read from the data "type" field
+#[derive(Deserialize)]
+struct JsonEvent {
+    #[serde(default)]
+    uuid: String,
+    #[serde(default)]
+    timestamp: i64,
+    #[serde(default)]
+    timestamp_millis: i64,
+    #[serde(default)]
+    timestamp_micros: i64,
+    #[serde(flatten)]
+    data: Data,
+    #[serde(default)]
+    context: Context,
+    #[serde(default)]
+    consent: Option<Consent>,
+}
+
+impl From<JsonEvent> for Event {
+    fn from(event: JsonEvent) -> Self {
+        let event_type = match event.data {
+            Data::Page(_) => EventType::Page,
+            Data::Track(_) => EventType::Track,
+            Data::User(_) => EventType::User,
+        };
+        Event {
+            uuid: event.uuid,
+            timestamp: event.timestamp,
+            timestamp_millis: event.timestamp_millis,
+            timestamp_micros: event.timestamp_micros,
+            event_type,
+            data: event.data,
+            context: event.context,
+            consent: event.consent,
+        }
+    }
+}
+
+// JSON property values are sent as strings: arrays and objects as JSON text,
+// and null values are skipped
+fn property_value(value: Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(value) => Some(value),
+        Value::Number(number) if number.is_f64() => number.as_f64().map(|value| value.to_string()),
+        value => Some(value.to_string()),
+    }
+}
+
+// properties are kept in document order, which decides the outcome of key collisions
+struct DictVisitor;
+
+impl<'de> Visitor<'de> for DictVisitor {
+    type Value = Dict;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object of properties")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Dict, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut dict = Dict::new();
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            if let Some(value) = property_value(value) {
+                dict.push((key, value));
+            }
+        }
+        Ok(dict)
+    }
+}
+
+struct OrderedDict(Dict);
+
+impl<'de> Deserialize<'de> for OrderedDict {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(DictVisitor).map(OrderedDict)
+    }
+}
+
+fn deserialize_dict<'de, D>(deserializer: D) -> Result<Dict, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_map(DictVisitor)
+}
+
+fn deserialize_dicts<'de, D>(deserializer: D) -> Result<Vec<Dict>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let dicts: Vec<OrderedDict> = Vec::deserialize(deserializer)?;
+    Ok(dicts.into_iter().map(|OrderedDict(dict)| dict).collect())
+}
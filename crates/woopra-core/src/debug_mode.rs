@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+
+use crate::data_collection::{EdgeeRequest, HttpMethod};
+use crate::WOOPRA_HOST;
+
+// dry-run mode: requests are sent to an echo endpoint instead of Woopra,
+// with a human-readable dump of the mapped fields in the body
+#[derive(Debug, Default, Clone)]
+pub struct DebugMode {
+    pub enabled: bool,
+    pub endpoint: Option<String>,
+}
+
+pub(crate) fn build_debug_request(
+    querystring: &str,
+    endpoint: &str,
+    debug_endpoint: &str,
+    mirror_urls: &[String],
+) -> anyhow::Result<EdgeeRequest> {
+    let woopra_url = format!("{WOOPRA_HOST}{endpoint}?{querystring}");
+    let mut body = dump_fields(querystring, &woopra_url)?;
+    if !mirror_urls.is_empty() {
+        body.push_str("\n# mirrored to\n");
+        for url in mirror_urls.iter() {
+            body.push_str(&format!("GET {url}\n"));
+        }
+    }
+    let headers = vec![
+        (String::from("content-type"), String::from("text/plain")),
+        (String::from("content-length"), body.len().to_string()),
+        (String::from("x-woopra-debug"), String::from("true")),
+    ];
+
+    Ok(EdgeeRequest {
+        method: HttpMethod::Post,
+        url: format!(
+            "{}{endpoint}?{querystring}",
+            debug_endpoint.trim_end_matches('/')
+        ),
+        headers,
+        // client headers are not needed to validate mappings
+        forward_client_headers: false,
+        body,
+    })
+}
+
+// one "name = value" line per parameter, sorted by name
+fn dump_fields(querystring: &str, woopra_url: &str) -> anyhow::Result<String> {
+    let fields: BTreeMap<String, String> = serde_qs::from_str(querystring)?;
+    let width = fields
+        .keys()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or_default();
+
+    let mut dump = format!("GET {woopra_url}\n");
+    for (name, value) in fields.iter() {
+        dump.push_str(&format!("{name:<width$} = {value}\n"));
+    }
+    Ok(dump)
+}
@@ -0,0 +1,76 @@
+use anyhow::Context;
+use std::collections::BTreeMap;
+
+use crate::{WOOPRA_IDENTIFY_ENDPOINT, WOOPRA_TRACK_ENDPOINT, WOOPRA_UPDATE_ENDPOINT};
+
+// structured view of a Woopra request, as found in proxy logs: the custom properties are
+// grouped by scope with their cv_/ce_/cs_ prefix removed, the other parameters are kept as is
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DecodedRequest {
+    pub endpoint: String,
+    pub params: BTreeMap<String, String>,
+    pub visitor: BTreeMap<String, String>,
+    pub event: BTreeMap<String, String>,
+    pub session: BTreeMap<String, String>,
+}
+
+impl DecodedRequest {
+    pub fn project(&self) -> Option<&str> {
+        self.params.get("project").map(String::as_str)
+    }
+
+    // the Woopra event name, only set on track and update requests
+    pub fn event_name(&self) -> Option<&str> {
+        self.params.get("event").map(String::as_str)
+    }
+
+    // the id of the event (the Edgee event uuid), or of the updated event on update requests
+    pub fn event_id(&self) -> Option<&str> {
+        self.params.get("id").map(String::as_str)
+    }
+
+    pub fn is_identify(&self) -> bool {
+        self.endpoint == WOOPRA_IDENTIFY_ENDPOINT
+    }
+
+    pub fn is_update(&self) -> bool {
+        self.endpoint == WOOPRA_UPDATE_ENDPOINT
+    }
+}
+
+// parses a /track/ce, /track/update or /track/identify URL, either absolute or starting with the path
+pub fn decode_url(url: &str) -> anyhow::Result<DecodedRequest> {
+    let url = url.trim();
+    let (location, querystring) = url.split_once('?').unwrap_or((url, ""));
+    let endpoint = [
+        WOOPRA_TRACK_ENDPOINT,
+        WOOPRA_UPDATE_ENDPOINT,
+        WOOPRA_IDENTIFY_ENDPOINT,
+    ]
+    .into_iter()
+    .find(|endpoint| location.ends_with(endpoint))
+    .with_context(|| format!("Not a Woopra track, update or identify URL: {location}"))?;
+
+    let mut decoded = decode_querystring(querystring)?;
+    decoded.endpoint = endpoint.to_string();
+    Ok(decoded)
+}
+
+pub fn decode_querystring(querystring: &str) -> anyhow::Result<DecodedRequest> {
+    let fields: BTreeMap<String, String> = serde_qs::from_str(querystring)
+        .with_context(|| format!("Invalid Woopra query string: {querystring}"))?;
+
+    let mut decoded = DecodedRequest::default();
+    for (name, value) in fields {
+        if let Some(name) = name.strip_prefix("cv_") {
+            decoded.visitor.insert(name.to_string(), value);
+        } else if let Some(name) = name.strip_prefix("ce_") {
+            decoded.event.insert(name.to_string(), value);
+        } else if let Some(name) = name.strip_prefix("cs_") {
+            decoded.session.insert(name.to_string(), value);
+        } else {
+            decoded.params.insert(name, value);
+        }
+    }
+    Ok(decoded)
+}
@@ -0,0 +1,217 @@
+use std::str::FromStr;
+
+use crate::data_collection::{Dict, TrackData};
+use crate::monetary::{
+    currency_of, decimal_separator, format_amount, parse_amount, MonetaryNormalization,
+};
+
+// semantic events of the Segment e-commerce spec (https://segment.com/docs/connections/spec/ecommerce/v2/)
+const ECOMMERCE_EVENTS: &[&str] = &[
+    "Products Searched",
+    "Product List Viewed",
+    "Product List Filtered",
+    "Promotion Viewed",
+    "Promotion Clicked",
+    "Product Clicked",
+    "Product Viewed",
+    "Product Added",
+    "Product Removed",
+    "Cart Viewed",
+    "Checkout Started",
+    "Checkout Step Viewed",
+    "Checkout Step Completed",
+    "Payment Info Entered",
+    "Order Updated",
+    "Order Completed",
+    "Order Refunded",
+    "Order Cancelled",
+    "Coupon Entered",
+    "Coupon Applied",
+    "Coupon Denied",
+    "Coupon Removed",
+];
+
+// events describing a single product, whose product fields are top-level properties
+const PRODUCT_EVENTS: &[&str] = &[
+    "Product Clicked",
+    "Product Viewed",
+    "Product Added",
+    "Product Removed",
+];
+
+// Segment product fields and their Woopra names (Woopra properties are flat,
+// so product fields are namespaced to not be confused with order fields)
+const PRODUCT_PROPERTIES: &[(&str, &str)] = &[
+    ("sku", "product_sku"),
+    ("name", "product_name"),
+    ("category", "product_category"),
+    ("brand", "product_brand"),
+    ("variant", "product_variant"),
+    ("price", "product_price"),
+    ("quantity", "product_quantity"),
+    ("url", "product_url"),
+    ("image_url", "product_image_url"),
+    ("position", "product_position"),
+];
+
+// monetary properties, sent as plain decimal numbers
+const AMOUNT_PROPERTIES: &[&str] = &[
+    "revenue",
+    "total",
+    "subtotal",
+    "value",
+    "shipping",
+    "tax",
+    "discount",
+    "price",
+    "product_price",
+];
+
+const CURRENCY_PROPERTY: &str = "currency";
+
+// presets recognize semantic event names and map their standard properties
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EventPreset {
+    #[default]
+    None,
+    SegmentEcommerce,
+}
+
+impl FromStr for EventPreset {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => Ok(EventPreset::None),
+            "segment_ecommerce" => Ok(EventPreset::SegmentEcommerce),
+            other => anyhow::bail!("Invalid event preset: {other}"),
+        }
+    }
+}
+
+impl EventPreset {
+    // returns the track data with the properties of recognized events mapped,
+    // other events are returned unchanged; amounts are read in the locale of the monetary
+    // normalization, which is left to normalize its own properties so that amounts are only
+    // parsed once
+    pub(crate) fn apply(
+        &self,
+        data: &TrackData,
+        monetary: &MonetaryNormalization,
+        client_locale: &str,
+    ) -> TrackData {
+        match self {
+            EventPreset::None => data.clone(),
+            EventPreset::SegmentEcommerce => segment_ecommerce(data, monetary, client_locale),
+        }
+    }
+}
+
+fn segment_ecommerce(
+    data: &TrackData,
+    monetary: &MonetaryNormalization,
+    client_locale: &str,
+) -> TrackData {
+    if !is_one_of(&data.name, ECOMMERCE_EVENTS) {
+        return data.clone();
+    }
+    let decimal = decimal_separator(monetary.locale(client_locale));
+    let product_event = is_one_of(&data.name, PRODUCT_EVENTS);
+
+    let mut properties = Dict::new();
+    let mut currency = None;
+    for (key, value) in data.properties.iter() {
+        let key = match PRODUCT_PROPERTIES.iter().find(|(name, _)| name == key) {
+            Some((_, woopra_name)) if product_event => woopra_name.to_string(),
+            _ => key.clone(),
+        };
+        let value = if AMOUNT_PROPERTIES.contains(&key.as_str()) {
+            if currency.is_none() {
+                currency = currency_of(value);
+            }
+            // left to the monetary normalization, amounts are only parsed once
+            if monetary.is_monetary(&key) {
+                value.clone()
+            } else {
+                normalize_amount(value, decimal)
+            }
+        } else if key == CURRENCY_PROPERTY {
+            value.trim().to_ascii_uppercase()
+        } else {
+            value.clone()
+        };
+        properties.push((key, value));
+    }
+    if let Some(currency) = currency.or(monetary.default_currency.as_deref()) {
+        if !properties.iter().any(|(key, _)| key == CURRENCY_PROPERTY) {
+            properties.push((CURRENCY_PROPERTY.to_string(), currency.to_string()));
+        }
+    }
+    properties.extend(summarize_products(&data.products));
+
+    TrackData {
+        name: data.name.clone(),
+        properties,
+        products: data.products.clone(),
+    }
+}
+
+// the products of cart and order events are summarized, as Woopra properties are flat:
+// number of products, total quantity, and comma-separated ids and names
+fn summarize_products(products: &[Dict]) -> Dict {
+    if products.is_empty() {
+        return Dict::new();
+    }
+    let field = |product: &Dict, names: &[&str]| {
+        names.iter().find_map(|name| {
+            product
+                .iter()
+                .find(|(key, value)| key == name && !value.is_empty())
+                .map(|(_, value)| value.clone())
+        })
+    };
+    let quantity: u64 = products
+        .iter()
+        .map(|product| {
+            field(product, &["quantity"])
+                .and_then(|quantity| quantity.trim().parse().ok())
+                .unwrap_or(1)
+        })
+        .sum();
+    let list = |names: &[&str]| {
+        products
+            .iter()
+            .filter_map(|product| field(product, names))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    let mut summary = vec![
+        ("product_count".to_string(), products.len().to_string()),
+        ("product_quantity".to_string(), quantity.to_string()),
+    ];
+    let ids = list(&["product_id", "sku"]);
+    if !ids.is_empty() {
+        summary.push(("product_ids".to_string(), ids));
+    }
+    let names = list(&["name"]);
+    if !names.is_empty() {
+        summary.push(("product_names".to_string(), names));
+    }
+    summary
+}
+
+// event names are matched case-insensitively, e.g. "order completed"
+fn is_one_of(name: &str, events: &[&str]) -> bool {
+    events
+        .iter()
+        .any(|event| event.eq_ignore_ascii_case(name.trim()))
+}
+
+// "$12.50", " 12,50 " and "12.5" are all sent as "12.5", and the decimal separator of the
+// locale decides whether "1.234" is 1.234 or 1234; values that are not amounts are sent as is
+fn normalize_amount(value: &str, decimal: Option<char>) -> String {
+    parse_amount(value, decimal)
+        .map(format_amount)
+        .unwrap_or_else(|| value.to_string())
+}
@@ -0,0 +1,36 @@
+use crate::url_canonicalization::{host_matches, url_host};
+
+// which events are forwarded to Woopra
+#[derive(Debug, Clone)]
+pub struct EventControls {
+    pub page_enabled: bool,
+    pub track_enabled: bool,
+    pub user_enabled: bool,
+    // if not empty, page events are only forwarded for these hosts ("*.example.com" matches subdomains)
+    pub page_hosts: Vec<String>,
+}
+
+impl Default for EventControls {
+    fn default() -> Self {
+        EventControls {
+            page_enabled: true,
+            track_enabled: true,
+            user_enabled: true,
+            page_hosts: vec![],
+        }
+    }
+}
+
+impl EventControls {
+    pub(crate) fn is_page_host_allowed(&self, url: &str) -> bool {
+        if self.page_hosts.is_empty() {
+            return true;
+        }
+        let Some(host) = url_host(url) else {
+            return false;
+        };
+        self.page_hosts
+            .iter()
+            .any(|allowed| host_matches(allowed, host))
+    }
+}
@@ -0,0 +1,231 @@
+// sample events shared by the unit tests of this crate and the tests of the component
+// (enabled by the "fixtures" feature outside of this crate)
+use uuid::Uuid;
+
+use crate::data_collection::{
+    Campaign, Client, Consent, Context, Data, Event, EventType, PageData, Session, TrackData,
+    UserData,
+};
+
+pub fn sample_user_data(edgee_id: String) -> UserData {
+    UserData {
+        user_id: "123".to_string(),
+        anonymous_id: "456".to_string(),
+        edgee_id,
+        properties: vec![
+            ("prop1".to_string(), "value1".to_string()),
+            ("prop2".to_string(), "10".to_string()),
+        ],
+    }
+}
+
+pub fn sample_user_data_without_properties(edgee_id: String) -> UserData {
+    UserData {
+        user_id: "123".to_string(),
+        anonymous_id: "456".to_string(),
+        edgee_id,
+        properties: vec![],
+    }
+}
+
+pub fn sample_user_data_with_cv_properties(edgee_id: String) -> UserData {
+    UserData {
+        user_id: "123".to_string(),
+        anonymous_id: "456".to_string(),
+        edgee_id,
+        properties: vec![
+            ("prop_name".to_string(), "value1".to_string()), // will be prefixed with cv_
+            ("cv_prop_ok".to_string(), "10".to_string()),    // won't be prefixed with cv_
+        ],
+    }
+}
+
+pub fn sample_context(edgee_id: String, locale: String, session_start: bool) -> Context {
+    Context {
+        page: sample_page_data(),
+        user: sample_user_data(edgee_id),
+        client: Client {
+            city: "Paris".to_string(),
+            ip: "192.168.0.1".to_string(),
+            locale,
+            timezone: "CET".to_string(),
+            user_agent: "Chrome".to_string(),
+            user_agent_architecture: "unknown".to_string(),
+            user_agent_bitness: "64".to_string(),
+            user_agent_full_version_list: "abc".to_string(),
+            user_agent_version_list: "abc".to_string(),
+            user_agent_mobile: "mobile".to_string(),
+            user_agent_model: "unknown".to_string(),
+            os_name: "MacOS".to_string(),
+            os_version: "latest".to_string(),
+            screen_width: 1024,
+            screen_height: 768,
+            screen_density: 2.0,
+            continent: "Europe".to_string(),
+            country_code: "FR".to_string(),
+            country_name: "France".to_string(),
+            region: "West Europe".to_string(),
+        },
+        campaign: Campaign {
+            name: "random".to_string(),
+            source: "random".to_string(),
+            medium: "random".to_string(),
+            term: "random".to_string(),
+            content: "random".to_string(),
+            creative_format: "random".to_string(),
+            marketing_tactic: "random".to_string(),
+        },
+        session: Session {
+            session_id: "random".to_string(),
+            previous_session_id: "random".to_string(),
+            session_count: 2,
+            session_start,
+            first_seen: 123,
+            last_seen: 123,
+        },
+    }
+}
+
+pub fn sample_page_data() -> PageData {
+    PageData {
+        name: "page name".to_string(),
+        category: "category".to_string(),
+        keywords: vec!["value1".to_string(), "value2".into()],
+        title: "page title".to_string(),
+        url: "https://example.com/full-url?test=1".to_string(),
+        path: "/full-path".to_string(),
+        search: "?test=1".to_string(),
+        referrer: "https://example.com/another-page".to_string(),
+        properties: vec![
+            ("prop1".to_string(), "value1".to_string()),
+            ("prop2".to_string(), "10".to_string()),
+            ("currency".to_string(), "USD".to_string()),
+        ],
+    }
+}
+
+pub fn sample_track_data(event_name: String) -> TrackData {
+    TrackData {
+        name: event_name,
+        products: vec![],
+        properties: vec![
+            ("prop1".to_string(), "value1".to_string()),
+            ("prop2".to_string(), "10".to_string()),
+            ("currency".to_string(), "USD".to_string()),
+        ],
+    }
+}
+
+pub fn sample_track_data_without_properties(event_name: String) -> TrackData {
+    TrackData {
+        name: event_name,
+        products: vec![],
+        properties: vec![],
+    }
+}
+
+pub fn sample_track_data_with_already_prefixed_properties(event_name: String) -> TrackData {
+    TrackData {
+        name: event_name,
+        products: vec![],
+        properties: vec![
+            ("prop_name".to_string(), "value1".to_string()),
+            ("ce_prop_ok".to_string(), "10".to_string()),
+        ],
+    }
+}
+
+pub fn sample_page_event(
+    consent: Option<Consent>,
+    edgee_id: String,
+    locale: String,
+    session_start: bool,
+) -> Event {
+    Event {
+        uuid: Uuid::new_v4().to_string(),
+        timestamp: 123,
+        timestamp_millis: 123,
+        timestamp_micros: 123,
+        event_type: EventType::Page,
+        data: Data::Page(sample_page_data()),
+        context: sample_context(edgee_id, locale, session_start),
+        consent,
+    }
+}
+
+pub fn sample_track_event(
+    event_name: String,
+    consent: Option<Consent>,
+    edgee_id: String,
+    locale: String,
+    session_start: bool,
+) -> Event {
+    Event {
+        uuid: Uuid::new_v4().to_string(),
+        timestamp: 123,
+        timestamp_millis: 123,
+        timestamp_micros: 123,
+        event_type: EventType::Track,
+        data: Data::Track(sample_track_data(event_name)),
+        context: sample_context(edgee_id, locale, session_start),
+        consent,
+    }
+}
+
+pub fn sample_track_event_without_properties(
+    event_name: String,
+    consent: Option<Consent>,
+    edgee_id: String,
+    locale: String,
+    session_start: bool,
+) -> Event {
+    Event {
+        uuid: Uuid::new_v4().to_string(),
+        timestamp: 123,
+        timestamp_millis: 123,
+        timestamp_micros: 123,
+        event_type: EventType::Track,
+        data: Data::Track(sample_track_data_without_properties(event_name)),
+        context: sample_context(edgee_id, locale, session_start),
+        consent,
+    }
+}
+
+pub fn sample_track_event_with_already_prefixed_properties(
+    event_name: String,
+    consent: Option<Consent>,
+    edgee_id: String,
+    locale: String,
+    session_start: bool,
+) -> Event {
+    Event {
+        uuid: Uuid::new_v4().to_string(),
+        timestamp: 123,
+        timestamp_millis: 123,
+        timestamp_micros: 123,
+        event_type: EventType::Track,
+        data: Data::Track(sample_track_data_with_already_prefixed_properties(
+            event_name,
+        )),
+        context: sample_context(edgee_id, locale, session_start),
+        consent,
+    }
+}
+
+pub fn sample_user_event(
+    consent: Option<Consent>,
+    edgee_id: String,
+    locale: String,
+    session_start: bool,
+) -> Event {
+    Event {
+        uuid: Uuid::new_v4().to_string(),
+        timestamp: 123,
+        timestamp_millis: 123,
+        timestamp_micros: 123,
+        event_type: EventType::User,
+        data: Data::User(sample_user_data_without_properties(edgee_id.clone())),
+        context: sample_context(edgee_id, locale, session_start),
+        consent,
+    }
+}
@@ -0,0 +1,38 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::pii::to_hex;
+
+// keyed hashing (HMAC-SHA256) of user identifiers, so that Woopra profiles still join up
+// across track and identify requests without exposing internal ids
+#[derive(Debug, Default, Clone)]
+pub struct IdentifierHashing {
+    // secret key, hashing is disabled if not set
+    pub key: Option<String>,
+    // user properties that are hashed too (e.g. "email"), user_id and anonymous_id always are
+    pub properties: Vec<String>,
+}
+
+impl IdentifierHashing {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.key.is_some()
+    }
+
+    // user properties can be listed with or without their "cv_" prefix
+    pub(crate) fn is_hashed_property(&self, key: &str) -> bool {
+        let key = key.strip_prefix("cv_").unwrap_or(key);
+        self.properties
+            .iter()
+            .any(|property| property.strip_prefix("cv_").unwrap_or(property) == key)
+    }
+
+    pub(crate) fn hash(&self, value: &str) -> String {
+        let Some(key) = self.key.as_ref() else {
+            return value.to_string();
+        };
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any size");
+        mac.update(value.as_bytes());
+        to_hex(&mac.finalize().into_bytes())
+    }
+}
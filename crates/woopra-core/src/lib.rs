@@ -0,0 +1,2713 @@
+use anyhow::Context;
+use data_collection::{Data, Dict, EdgeeRequest, Event, HttpMethod};
+use std::collections::HashMap;
+use woopra_payload::{
+    WoopraPayload, WoopraPayloadIdentify, WoopraPayloadTrack, WoopraPayloadUpdate,
+};
+
+pub use debug_mode::DebugMode;
+pub use decoding::{decode_querystring, decode_url, DecodedRequest};
+pub use ecommerce::EventPreset;
+pub use event_controls::EventControls;
+pub use identifier_hashing::IdentifierHashing;
+pub use link_tracking::LinkTracking;
+pub use mirroring::Mirroring;
+pub use monetary::MonetaryNormalization;
+pub use pii::{PiiAction, PiiScrubbing};
+pub use project_routing::ProjectRouting;
+pub use properties::{CollisionPolicy, PropertyScope, ScopeRouting};
+pub use sampling::Sampling;
+pub use url_canonicalization::UrlCanonicalization;
+
+pub mod data_collection;
+mod debug_mode;
+mod decoding;
+mod ecommerce;
+mod event_controls;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+mod identifier_hashing;
+mod link_tracking;
+mod mirroring;
+mod monetary;
+mod pii;
+mod project_routing;
+mod properties;
+mod sampling;
+mod url_canonicalization;
+mod woopra_payload;
+
+const WOOPRA_HOST: &str = "https://www.woopra.com";
+const WOOPRA_TRACK_ENDPOINT: &str = "/track/ce";
+const WOOPRA_IDENTIFY_ENDPOINT: &str = "/track/identify";
+const WOOPRA_UPDATE_ENDPOINT: &str = "/track/update";
+const DEFAULT_APP_NAME: &str = "Edgee";
+
+// maps a page event to a Woopra track request (event "pv")
+pub fn page(edgee_event: &Event, settings: &Settings) -> anyhow::Result<EdgeeRequest> {
+    if let Data::Page(ref data) = edgee_event.data {
+        if !settings.event_controls.page_enabled {
+            anyhow::bail!("Page events are disabled");
+        }
+        let page_url = if data.url.is_empty() {
+            &edgee_event.context.page.url
+        } else {
+            &data.url
+        };
+        if !settings.event_controls.is_page_host_allowed(page_url) {
+            // only the host is reported, the full URL could carry personal data
+            match url_canonicalization::url_host(page_url) {
+                Some(host) => anyhow::bail!("Page events are disabled for this host: {host}"),
+                None => anyhow::bail!("Page events are disabled for pages without a host"),
+            }
+        }
+        if !settings.sampling.is_kept(&edgee_event.context.user) {
+            anyhow::bail!("Event dropped by sampling");
+        }
+
+        let project = settings.project_routing.resolve(
+            &settings.project_name,
+            page_url,
+            &[&data.properties, &edgee_event.context.page.properties],
+        );
+
+        let mut payload = WoopraPayloadTrack::new(edgee_event, project, "pv".to_string())?;
+
+        payload.set_page_fields(data);
+        payload.add_context_properties(edgee_event, &settings.scope_routing);
+        payload.add_page_properties(data, &settings.scope_routing);
+        payload.add_default_properties(&settings.default_properties);
+        payload.set_default_timeout(settings.idle_timeout);
+        payload.set_app(&settings.app_name);
+        if settings.component_metadata {
+            payload.add_component_metadata();
+        }
+        if settings.sampling.is_enabled() {
+            payload.add_sample_rate(settings.sampling.rate);
+        }
+        payload.resolve_collisions(settings.collision_policy);
+        payload.canonicalize_urls(&settings.url_canonicalization);
+        payload.hash_identifiers(&settings.identifier_hashing);
+        payload.scrub_pii(&settings.pii_scrubbing);
+        // set after scrubbing, the uuid is never altered
+        if settings.send_event_id {
+            payload.set_event_id(&edgee_event.uuid);
+        }
+
+        build_woopra_request(&mut payload, WOOPRA_TRACK_ENDPOINT, settings)
+    } else {
+        anyhow::bail!("Missing page data")
+    }
+}
+
+// maps a track event to a Woopra track request, or to an update request of a previous event
+pub fn track(edgee_event: &Event, settings: &Settings) -> anyhow::Result<EdgeeRequest> {
+    if let Data::Track(ref data) = edgee_event.data {
+        if data.name.is_empty() {
+            anyhow::bail!("Track is not set");
+        }
+        if !settings.event_controls.track_enabled {
+            anyhow::bail!("Track events are disabled");
+        }
+        if !settings.sampling.is_kept(&edgee_event.context.user) {
+            anyhow::bail!("Event dropped by sampling");
+        }
+        let data = &settings.event_preset.apply(
+            data,
+            &settings.monetary_normalization,
+            &edgee_event.context.client.locale,
+        );
+        let data = &settings
+            .link_tracking
+            .apply(data, &edgee_event.context.page.url);
+        let data = &settings
+            .monetary_normalization
+            .apply(data, &edgee_event.context.client.locale);
+
+        let project = settings.project_routing.resolve(
+            &settings.project_name,
+            &edgee_event.context.page.url,
+            &[&data.properties, &edgee_event.context.page.properties],
+        );
+
+        // a track event carrying the id of a previous event updates its properties instead
+        if let Some(id) = woopra_payload::update_id(data) {
+            let mut payload = WoopraPayloadUpdate::new(project, data.name.clone(), id);
+            payload.add_track_properties(data, &settings.scope_routing);
+            payload.resolve_collisions(settings.collision_policy);
+            payload.scrub_pii(&settings.pii_scrubbing);
+            return build_woopra_request(&mut payload, WOOPRA_UPDATE_ENDPOINT, settings);
+        }
+
+        let mut payload = WoopraPayloadTrack::new(edgee_event, project, data.name.clone())?;
+
+        payload.add_context_properties(edgee_event, &settings.scope_routing);
+        payload.add_track_properties(data, &settings.scope_routing);
+        payload.add_default_properties(&settings.default_properties);
+        payload.set_default_timeout(settings.idle_timeout);
+        payload.set_app(&settings.app_name);
+        if settings.component_metadata {
+            payload.add_component_metadata();
+        }
+        if settings.sampling.is_enabled() {
+            payload.add_sample_rate(settings.sampling.rate);
+        }
+        payload.resolve_collisions(settings.collision_policy);
+        payload.canonicalize_urls(&settings.url_canonicalization);
+        payload.hash_identifiers(&settings.identifier_hashing);
+        payload.scrub_pii(&settings.pii_scrubbing);
+        // set after scrubbing, the uuid is never altered
+        if settings.send_event_id {
+            payload.set_event_id(&edgee_event.uuid);
+        }
+
+        build_woopra_request(&mut payload, WOOPRA_TRACK_ENDPOINT, settings)
+    } else {
+        anyhow::bail!("Missing track data")
+    }
+}
+
+// maps a user event to a Woopra identify request
+pub fn user(edgee_event: &Event, settings: &Settings) -> anyhow::Result<EdgeeRequest> {
+    if let Data::User(ref data) = edgee_event.data {
+        if !settings.event_controls.user_enabled {
+            anyhow::bail!("User events are disabled");
+        }
+        if !settings.sampling.is_kept(&edgee_event.context.user) {
+            anyhow::bail!("Event dropped by sampling");
+        }
+
+        let project = settings.project_routing.resolve(
+            &settings.project_name,
+            &edgee_event.context.page.url,
+            &[&data.properties, &edgee_event.context.user.properties],
+        );
+
+        let mut payload = WoopraPayloadIdentify::new(edgee_event, project)?;
+
+        payload.add_user_properties(data);
+        payload.add_default_properties(&settings.default_properties);
+        if settings.sampling.is_enabled() {
+            payload.add_sample_rate(settings.sampling.rate);
+        }
+        payload.resolve_collisions(settings.collision_policy);
+        payload.hash_identifiers(&settings.identifier_hashing);
+        payload.scrub_pii(&settings.pii_scrubbing);
+
+        build_woopra_request(&mut payload, WOOPRA_IDENTIFY_ENDPOINT, settings)
+    } else {
+        anyhow::bail!("Missing user data")
+    }
+}
+
+// maps any event to its Woopra request, dispatching on the kind of data it carries
+pub fn build_request(edgee_event: &Event, settings: &Settings) -> anyhow::Result<EdgeeRequest> {
+    match edgee_event.data {
+        Data::Page(_) => page(edgee_event, settings),
+        Data::Track(_) => track(edgee_event, settings),
+        Data::User(_) => user(edgee_event, settings),
+    }
+}
+
+// serialize the payload and build the request, or the debug request if debug mode is enabled,
+// or the fan-out request if mirroring is enabled
+fn build_woopra_request<P: WoopraPayload>(
+    payload: &mut P,
+    endpoint: &str,
+    settings: &Settings,
+) -> anyhow::Result<EdgeeRequest> {
+    let querystring = serde_qs::to_string(payload)?;
+
+    let mirroring = &settings.mirroring;
+    let mut mirror_urls = Vec::with_capacity(mirroring.projects.len());
+    for project in mirroring.projects.iter() {
+        payload.set_project(project);
+        let querystring = serde_qs::to_string(payload)?;
+        mirror_urls.push(format!("{WOOPRA_HOST}{endpoint}?{querystring}"));
+    }
+
+    if settings.debug_mode.enabled {
+        let debug_endpoint = settings
+            .debug_mode
+            .endpoint
+            .as_ref()
+            .context("Missing debug_endpoint setting")?;
+        // mirrored requests are not sent either, they are listed in the dump
+        return debug_mode::build_debug_request(
+            &querystring,
+            endpoint,
+            debug_endpoint,
+            &mirror_urls,
+        );
+    }
+
+    if !mirroring.is_enabled() {
+        return build_edgee_request(querystring, endpoint.to_string());
+    }
+
+    let mut urls = vec![format!("{WOOPRA_HOST}{endpoint}?{querystring}")];
+    urls.extend(mirror_urls);
+    let fanout_endpoint = mirroring
+        .fanout_endpoint
+        .as_ref()
+        .context("Missing mirror_fanout_endpoint setting")?;
+
+    Ok(mirroring::build_fanout_request(&urls, fanout_endpoint))
+}
+
+fn build_edgee_request(querystring: String, endpoint: String) -> anyhow::Result<EdgeeRequest> {
+    let headers = vec![(String::from("content-length"), String::from("0"))];
+
+    Ok(EdgeeRequest {
+        method: HttpMethod::Get,
+        url: format!("{WOOPRA_HOST}{endpoint}?{querystring}"),
+        headers,
+        forward_client_headers: true,
+        body: String::new(),
+    })
+}
+
+// component settings, parsed from the raw settings dictionary
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub project_name: String,
+    pub url_canonicalization: UrlCanonicalization,
+    pub pii_scrubbing: PiiScrubbing,
+    pub identifier_hashing: IdentifierHashing,
+    pub default_properties: DefaultProperties,
+    pub event_controls: EventControls,
+    pub idle_timeout: Option<u64>, // in milliseconds
+    pub app_name: String,
+    pub component_metadata: bool,
+    pub project_routing: ProjectRouting,
+    pub mirroring: Mirroring,
+    pub sampling: Sampling,
+    pub debug_mode: DebugMode,
+    pub collision_policy: CollisionPolicy,
+    pub scope_routing: ScopeRouting,
+    pub event_preset: EventPreset,
+    pub link_tracking: LinkTracking,
+    pub monetary_normalization: MonetaryNormalization,
+    pub send_event_id: bool,
+}
+
+// static properties attached to every event (event-supplied values take precedence)
+#[derive(Debug, Default, Clone)]
+pub struct DefaultProperties {
+    pub visitor: Vec<(String, String)>,
+    pub event: Vec<(String, String)>,
+    pub session: Vec<(String, String)>,
+}
+
+impl Settings {
+    pub fn new(settings_dict: Dict) -> anyhow::Result<Self> {
+        let settings_map: HashMap<String, String> = settings_dict
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        let project_name = settings_map
+            .get("project_name")
+            .context("Missing example setting")?
+            .to_string();
+
+        let url_canonicalization = UrlCanonicalization {
+            strip_query_params: parse_list(&settings_map, "url_strip_query_params")
+                .into_iter()
+                .map(|param| param.to_ascii_lowercase())
+                .collect(),
+            allowed_query_params: parse_list(&settings_map, "url_allowed_query_params")
+                .into_iter()
+                .map(|param| param.to_ascii_lowercase())
+                .collect(),
+            strip_fragment: parse_bool(&settings_map, "url_strip_fragment", false)?,
+            lowercase_host: parse_bool(&settings_map, "url_lowercase_host", false)?,
+        };
+
+        let pii_detectors = parse_list(&settings_map, "pii_detectors");
+        let pii_scrubbing = PiiScrubbing {
+            action: settings_map
+                .get("pii_action")
+                .map(|value| value.parse())
+                .transpose()?
+                .unwrap_or_default(),
+            detect_emails: pii_detectors.is_empty() || pii_detectors.iter().any(|d| d == "email"),
+            detect_phones: pii_detectors.is_empty() || pii_detectors.iter().any(|d| d == "phone"),
+            detect_credit_cards: pii_detectors.is_empty()
+                || pii_detectors.iter().any(|d| d == "credit_card"),
+            exempt_fields: parse_list(&settings_map, "pii_exempt_fields"),
+        };
+
+        let identifier_hashing = IdentifierHashing {
+            key: settings_map
+                .get("user_id_hashing_key")
+                .filter(|key| !key.is_empty())
+                .cloned(),
+            properties: parse_list(&settings_map, "hashed_user_properties"),
+        };
+
+        let default_properties = DefaultProperties {
+            visitor: parse_map(&settings_map, "default_visitor_properties")?,
+            event: parse_map(&settings_map, "default_event_properties")?,
+            session: parse_map(&settings_map, "default_session_properties")?,
+        };
+
+        let event_controls = EventControls {
+            page_enabled: parse_bool(&settings_map, "edgee_page_event_enabled", true)?,
+            track_enabled: parse_bool(&settings_map, "edgee_track_event_enabled", true)?,
+            user_enabled: parse_bool(&settings_map, "edgee_user_event_enabled", true)?,
+            page_hosts: parse_list(&settings_map, "page_event_hosts"),
+        };
+
+        let mirroring = Mirroring {
+            projects: parse_list(&settings_map, "mirror_projects"),
+            fanout_endpoint: settings_map
+                .get("mirror_fanout_endpoint")
+                .map(|endpoint| endpoint.trim().to_string())
+                .filter(|endpoint| !endpoint.is_empty()),
+        };
+        if mirroring.is_enabled() && mirroring.fanout_endpoint.is_none() {
+            anyhow::bail!("Missing mirror_fanout_endpoint setting");
+        }
+
+        let mut link_tracking = LinkTracking {
+            enabled: parse_bool(&settings_map, "link_tracking", false)?,
+            ..LinkTracking::default()
+        };
+        if let Some(property) = settings_map
+            .get("link_url_property")
+            .map(|property| property.trim())
+            .filter(|property| !property.is_empty())
+        {
+            link_tracking.url_property = property.to_string();
+        }
+        let download_extensions = parse_list(&settings_map, "download_extensions");
+        if !download_extensions.is_empty() {
+            link_tracking.download_extensions = download_extensions
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_ascii_lowercase())
+                .collect();
+        }
+
+        let monetary_normalization = MonetaryNormalization {
+            properties: parse_list(&settings_map, "monetary_properties"),
+            locale: settings_map
+                .get("monetary_locale")
+                .map(|locale| locale.trim().to_string())
+                .filter(|locale| !locale.is_empty()),
+            currency_property: settings_map
+                .get("currency_property")
+                .map(|property| property.trim().to_string())
+                .filter(|property| !property.is_empty())
+                .unwrap_or_else(|| MonetaryNormalization::default().currency_property),
+            default_currency: settings_map
+                .get("default_currency")
+                .map(|currency| currency.trim().to_ascii_uppercase())
+                .filter(|currency| !currency.is_empty())
+                .map(|currency| parse_currency(&currency, "default_currency"))
+                .transpose()?,
+            base_currency: settings_map
+                .get("base_currency")
+                .map(|currency| currency.trim().to_ascii_uppercase())
+                .filter(|currency| !currency.is_empty())
+                .map(|currency| parse_currency(&currency, "base_currency"))
+                .transpose()?,
+            rates: parse_map(&settings_map, "currency_rates")?
+                .into_iter()
+                .map(|(currency, rate)| {
+                    let currency =
+                        parse_currency(&currency.to_ascii_uppercase(), "currency_rates")?;
+                    let rate = rate
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|rate| rate.is_finite() && *rate > 0.0)
+                        .with_context(|| {
+                            format!("Invalid rate for setting currency_rates: {rate}")
+                        })?;
+                    Ok((currency, rate))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        };
+
+        let sampling = match settings_map.get("sample_rate").map(|rate| rate.trim()) {
+            None | Some("") => Sampling::default(),
+            Some(rate) => Sampling {
+                rate: rate
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|rate| (0.0..=1.0).contains(rate))
+                    .with_context(|| format!("Invalid sample_rate setting: {rate}"))?,
+            },
+        };
+
+        let debug_mode = DebugMode {
+            enabled: parse_bool(&settings_map, "debug_mode", false)?,
+            endpoint: settings_map
+                .get("debug_endpoint")
+                .map(|endpoint| endpoint.trim().to_string())
+                .filter(|endpoint| !endpoint.is_empty()),
+        };
+        if debug_mode.enabled && debug_mode.endpoint.is_none() {
+            anyhow::bail!("Missing debug_endpoint setting");
+        }
+
+        Ok(Self {
+            project_name,
+            url_canonicalization,
+            pii_scrubbing,
+            identifier_hashing,
+            default_properties,
+            event_controls,
+            idle_timeout: parse_number(&settings_map, "idle_timeout")?,
+            app_name: settings_map
+                .get("app_name")
+                .map(|app_name| app_name.trim().to_string())
+                .filter(|app_name| !app_name.is_empty())
+                .unwrap_or_else(|| DEFAULT_APP_NAME.to_string()),
+            component_metadata: parse_bool(&settings_map, "send_component_metadata", false)?,
+            project_routing: ProjectRouting {
+                routes: parse_map(&settings_map, "project_routes")?,
+                property: settings_map
+                    .get("project_routing_property")
+                    .map(|property| property.trim().to_string())
+                    .filter(|property| !property.is_empty()),
+            },
+            mirroring,
+            sampling,
+            debug_mode,
+            collision_policy: settings_map
+                .get("property_collision_policy")
+                .map(|value| value.parse())
+                .transpose()?
+                .unwrap_or_default(),
+            scope_routing: ScopeRouting {
+                routes: parse_map(&settings_map, "property_scopes")?
+                    .into_iter()
+                    .map(|(name, scope)| Ok((name, scope.parse()?)))
+                    .collect::<anyhow::Result<_>>()?,
+            },
+            event_preset: settings_map
+                .get("event_preset")
+                .map(|value| value.parse())
+                .transpose()?
+                .unwrap_or_default(),
+            link_tracking,
+            monetary_normalization,
+            send_event_id: parse_bool(&settings_map, "send_event_id", true)?,
+        })
+    }
+}
+
+// boolean settings are optional, missing or empty values fall back to the default
+fn parse_bool(
+    settings_map: &HashMap<String, String>,
+    key: &str,
+    default: bool,
+) -> anyhow::Result<bool> {
+    match settings_map.get(key).map(|value| value.trim()) {
+        None | Some("") => Ok(default),
+        Some(value) => value
+            .to_ascii_lowercase()
+            .parse::<bool>()
+            .with_context(|| format!("Invalid boolean value for setting {key}: {value}")),
+    }
+}
+
+// number settings are optional and must be positive integers
+fn parse_number(settings_map: &HashMap<String, String>, key: &str) -> anyhow::Result<Option<u64>> {
+    match settings_map.get(key).map(|value| value.trim()) {
+        None | Some("") => Ok(None),
+        Some(value) => value
+            .parse::<u64>()
+            .ok()
+            .filter(|number| *number > 0)
+            .map(Some)
+            .with_context(|| format!("Invalid number for setting {key}: {value}")),
+    }
+}
+
+// list settings are comma-separated, empty items are ignored
+fn parse_list(settings_map: &HashMap<String, String>, key: &str) -> Vec<String> {
+    settings_map
+        .get(key)
+        .map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// currency settings are ISO 4217 codes
+fn parse_currency(currency: &str, key: &str) -> anyhow::Result<String> {
+    if !monetary::is_iso_currency(currency) {
+        anyhow::bail!("Invalid currency for setting {key}: {currency}");
+    }
+    Ok(currency.to_string())
+}
+
+// map settings are comma-separated "key=value" pairs
+fn parse_map(
+    settings_map: &HashMap<String, String>,
+    key: &str,
+) -> anyhow::Result<Vec<(String, String)>> {
+    parse_list(settings_map, key)
+        .iter()
+        .map(|item| {
+            let (name, value) = item
+                .split_once('=')
+                .with_context(|| format!("Invalid key=value pair for setting {key}: {item}"))?;
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::data_collection::{Consent, EventType, UserData};
+    use crate::fixtures::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::BTreeMap;
+    use uuid::Uuid;
+
+    // runs a mapping function the same way the component does, from the raw settings
+    fn run(
+        handler: fn(&Event, &Settings) -> anyhow::Result<EdgeeRequest>,
+        event: Event,
+        settings_dict: Dict,
+    ) -> Result<EdgeeRequest, String> {
+        let settings = Settings::new(settings_dict).map_err(|e| e.to_string())?;
+        handler(&event, &settings).map_err(|e| e.to_string())
+    }
+
+    fn query_params(url: &str) -> HashMap<String, String> {
+        let (_, querystring) = url.split_once('?').unwrap();
+        serde_qs::from_str(querystring).unwrap()
+    }
+
+    #[test]
+    fn track_event_with_empty_properties() {
+        let event = sample_track_event_without_properties(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![("project_name".to_string(), "example.com".to_string())];
+        let result = run(track, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["event"], "test_event");
+        assert_eq!(params.contains_key("ce_prop1"), false);
+    }
+
+    #[test]
+    fn track_event_with_already_prefixed_properties() {
+        let event = sample_track_event_with_already_prefixed_properties(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![("project_name".to_string(), "example.com".to_string())];
+        let result = run(track, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["ce_prop_name"], "value1");
+        assert_eq!(params["ce_prop_ok"], "10");
+        assert_eq!(params.contains_key("ce_ce_prop_ok"), false);
+    }
+
+    #[test]
+    fn user_event_with_already_prefixed_properties() {
+        let mut event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        event.data = Data::User(sample_user_data_with_cv_properties("abc".to_string()));
+        let settings = vec![("project_name".to_string(), "example.com".to_string())];
+        let result = run(user, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["cv_id"], "123");
+        assert_eq!(params["cv_prop_name"], "value1");
+        assert_eq!(params["cv_prop_ok"], "10");
+        assert_eq!(params.contains_key("cv_cv_prop_ok"), false);
+    }
+
+    #[test]
+    fn track_event_with_custom_session_properties() {
+        let mut event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        if let Data::Track(ref mut data) = event.data {
+            data.properties = vec![("cs_channel".to_string(), "ads".to_string())];
+        }
+        event
+            .context
+            .page
+            .properties
+            .push(("cs_landing".to_string(), "pricing".to_string()));
+        let settings = vec![("project_name".to_string(), "example.com".to_string())];
+        let result = run(track, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["cs_channel"], "ads");
+        assert_eq!(params["cs_landing"], "pricing");
+        assert_eq!(params["cs_session_id"], "random");
+        assert_eq!(params.contains_key("cs_cs_channel"), false);
+        assert_eq!(params.contains_key("ce_cs_channel"), false);
+        assert_eq!(params.contains_key("ce_page_cs_landing"), false);
+    }
+
+    #[test]
+    fn track_event_with_segment_ecommerce_preset() {
+        let mut event = sample_track_event(
+            "Order Completed".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        if let Data::Track(ref mut data) = event.data {
+            data.properties = vec![
+                ("order_id".to_string(), "50314b8e".to_string()),
+                ("revenue".to_string(), "$25,00".to_string()),
+                ("shipping".to_string(), "3".to_string()),
+                ("name".to_string(), "Spring sale".to_string()),
+            ];
+            data.products = vec![
+                vec![
+                    ("product_id".to_string(), "507f1f77".to_string()),
+                    ("name".to_string(), "Monopoly".to_string()),
+                    ("quantity".to_string(), "2".to_string()),
+                ],
+                vec![
+                    ("sku".to_string(), "G-32".to_string()),
+                    ("name".to_string(), "Uno".to_string()),
+                ],
+            ];
+        }
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("event_preset".to_string(), "segment_ecommerce".to_string()),
+        ];
+
+        let request = run(track, event.clone(), settings.clone()).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["event"], "Order Completed");
+        assert_eq!(params["ce_order_id"], "50314b8e");
+        assert_eq!(params["ce_revenue"], "25");
+        assert_eq!(params["ce_shipping"], "3");
+        // "$" is not only the symbol of USD
+        assert_eq!(params.contains_key("ce_currency"), false);
+        assert_eq!(params["ce_name"], "Spring sale");
+        assert_eq!(params["ce_product_count"], "2");
+        assert_eq!(params["ce_product_quantity"], "3");
+        assert_eq!(params["ce_product_ids"], "507f1f77,G-32");
+        assert_eq!(params["ce_product_names"], "Monopoly,Uno");
+
+        let mut with_default = settings.clone();
+        with_default.push(("default_currency".to_string(), "cad".to_string()));
+        let request = run(track, event.clone(), with_default).unwrap();
+        assert_eq!(query_params(&request.url)["ce_currency"], "CAD");
+
+        // product fields of single product events are namespaced
+        if let Data::Track(ref mut data) = event.data {
+            data.name = "product viewed".to_string();
+            data.properties = vec![
+                ("product_id".to_string(), "507f1f77".to_string()),
+                ("name".to_string(), "Monopoly".to_string()),
+                ("price".to_string(), "18,990".to_string()),
+                ("currency".to_string(), " eur".to_string()),
+            ];
+            data.products = vec![];
+        }
+        let request = run(track, event.clone(), settings.clone()).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_product_id"], "507f1f77");
+        assert_eq!(params["ce_product_name"], "Monopoly");
+        assert_eq!(params["ce_product_price"], "18.99");
+        assert_eq!(params["ce_currency"], "EUR");
+        assert_eq!(params.contains_key("ce_product_count"), false);
+
+        // ambiguous amounts are read in the locale of the client, or the monetary locale
+        let mut english = event.clone();
+        english.context.client.locale = "en-US".to_string();
+        let params = query_params(&run(track, english.clone(), settings.clone()).unwrap().url);
+        assert_eq!(params["ce_product_price"], "18990");
+        let mut french_settings = settings.clone();
+        french_settings.push(("monetary_locale".to_string(), "fr-FR".to_string()));
+        let params = query_params(&run(track, english, french_settings).unwrap().url);
+        assert_eq!(params["ce_product_price"], "18.99");
+
+        // other events and settings without preset are left untouched
+        let request = run(
+            track,
+            event.clone(),
+            vec![("project_name".to_string(), "example.com".to_string())],
+        )
+        .unwrap();
+        assert_eq!(query_params(&request.url)["ce_name"], "Monopoly");
+        if let Data::Track(ref mut data) = event.data {
+            data.name = "Signup".to_string();
+        }
+        let request = run(track, event.clone(), settings).unwrap();
+        assert_eq!(query_params(&request.url)["ce_price"], "18,990");
+
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("event_preset".to_string(), "ga4".to_string()),
+        ];
+        assert_eq!(run(track, event, settings).is_err(), true);
+    }
+
+    #[test]
+    fn same_event_always_yields_the_same_event_id() {
+        let event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![("project_name".to_string(), "example.com".to_string())];
+
+        // retries of the same event carry the same key
+        let first = query_params(&run(track, event.clone(), settings.clone()).unwrap().url);
+        let retry = query_params(&run(track, event.clone(), settings.clone()).unwrap().url);
+        assert_eq!(first["id"], event.uuid);
+        assert_eq!(first["ce_edgee_event_id"], event.uuid);
+        assert_eq!(retry["id"], first["id"]);
+        assert_eq!(retry["ce_edgee_event_id"], first["ce_edgee_event_id"]);
+
+        let mut other_event = event.clone();
+        other_event.uuid = Uuid::new_v4().to_string();
+        let other = query_params(&run(track, other_event, settings.clone()).unwrap().url);
+        assert_ne!(other["id"], first["id"]);
+
+        // page requests too, but not identify requests
+        let page_event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let params = query_params(&run(page, page_event.clone(), settings.clone()).unwrap().url);
+        assert_eq!(params["id"], page_event.uuid);
+        let user_event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let params = query_params(&run(user, user_event, settings.clone()).unwrap().url);
+        assert_eq!(params.contains_key("id"), false);
+
+        // mirrored requests share the key, and it is never scrubbed
+        let mirror_settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            (
+                "mirror_projects".to_string(),
+                "sandbox.example.com".to_string(),
+            ),
+            (
+                "mirror_fanout_endpoint".to_string(),
+                "https://proxy.example.com/fanout".to_string(),
+            ),
+            ("pii_action".to_string(), "redact".to_string()),
+        ];
+        let request = run(track, event.clone(), mirror_settings).unwrap();
+        assert_eq!(request.body.lines().count(), 2);
+        for url in request.body.lines() {
+            let params = query_params(url);
+            assert_eq!(params["id"], event.uuid);
+            assert_eq!(params["ce_edgee_event_id"], event.uuid);
+        }
+
+        let mut settings = settings;
+        settings.push(("send_event_id".to_string(), "false".to_string()));
+        let params = query_params(&run(track, event, settings).unwrap().url);
+        assert_eq!(params.contains_key("id"), false);
+        assert_eq!(params.contains_key("ce_edgee_event_id"), false);
+    }
+
+    #[test]
+    fn track_event_with_update_id_updates_previous_event() {
+        let mut event = sample_track_event(
+            "Order Completed".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        if let Data::Track(ref mut data) = event.data {
+            data.properties = vec![
+                ("woopra_update_id".to_string(), " 3f2a9c ".to_string()),
+                ("status".to_string(), "confirmed".to_string()),
+            ];
+        }
+        let settings = vec![("project_name".to_string(), "example.com".to_string())];
+
+        let request = run(track, event.clone(), settings.clone()).unwrap();
+        assert_eq!(
+            request
+                .url
+                .starts_with("https://www.woopra.com/track/update?"),
+            true
+        );
+        let decoded = decode_url(&request.url).unwrap();
+        assert_eq!(decoded.is_update(), true);
+        assert_eq!(decoded.event_id(), Some("3f2a9c"));
+        assert_eq!(decoded.event_name(), Some("Order Completed"));
+        assert_eq!(decoded.event.get("status").unwrap(), "confirmed");
+        assert_eq!(decoded.event.contains_key("woopra_update_id"), false);
+        assert_eq!(decoded.event.contains_key("edgee_event_id"), false);
+
+        // only the event properties are updated, the context of the update is not sent
+        if let Data::Track(ref mut data) = event.data {
+            data.properties.extend([
+                ("visitor.plan".to_string(), "pro".to_string()),
+                ("cs_channel".to_string(), "email".to_string()),
+                ("woopra_timeout".to_string(), "60000".to_string()),
+                ("event.step".to_string(), "2".to_string()),
+            ]);
+        }
+        let mut context_settings = settings.clone();
+        context_settings.extend([
+            (
+                "default_event_properties".to_string(),
+                "env=prod".to_string(),
+            ),
+            (
+                "default_visitor_properties".to_string(),
+                "tier=1".to_string(),
+            ),
+            ("sample_rate".to_string(), "0.99".to_string()),
+            ("idle_timeout".to_string(), "30000".to_string()),
+            ("app_name".to_string(), "brand-a".to_string()),
+            ("send_component_metadata".to_string(), "true".to_string()),
+        ]);
+        let request = run(track, event.clone(), context_settings).unwrap();
+        let decoded = decode_url(&request.url).unwrap();
+        assert_eq!(
+            decoded.params.keys().collect::<Vec<_>>(),
+            vec!["event", "id", "project"]
+        );
+        assert_eq!(
+            decoded.event,
+            BTreeMap::from([
+                ("status".to_string(), "confirmed".to_string()),
+                ("step".to_string(), "2".to_string()),
+            ])
+        );
+        assert_eq!(decoded.visitor.is_empty(), true);
+        assert_eq!(decoded.session.is_empty(), true);
+
+        // an empty id is ignored, the event is sent with its own id
+        if let Data::Track(ref mut data) = event.data {
+            data.properties[0].1 = String::new();
+        }
+        let request = run(track, event.clone(), settings).unwrap();
+        let decoded = decode_url(&request.url).unwrap();
+        assert_eq!(decoded.is_update(), false);
+        assert_eq!(decoded.event_id(), Some(event.uuid.as_str()));
+    }
+
+    #[test]
+    fn track_event_with_monetary_normalization() {
+        let event = sample_track_event(
+            "Purchase".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let amounts = |event: &Event, properties: &[(&str, &str)]| {
+            let mut event = event.clone();
+            if let Data::Track(ref mut data) = event.data {
+                data.properties = properties
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect();
+            }
+            event
+        };
+        let settings = |extra: &[(&str, &str)]| {
+            let mut settings = vec![
+                ("project_name".to_string(), "example.com".to_string()),
+                (
+                    "monetary_properties".to_string(),
+                    "revenue,price".to_string(),
+                ),
+            ];
+            settings.extend(
+                extra
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string())),
+            );
+            settings
+        };
+
+        // the client locale is "fr": "1.234" is grouped, "12,5" is a decimal
+        let request = run(
+            track,
+            amounts(
+                &event,
+                &[
+                    ("revenue", "1.234,50 €"),
+                    ("price", "12,5"),
+                    ("label", "3,14"),
+                ],
+            ),
+            settings(&[]),
+        )
+        .unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_revenue"], "1234.5");
+        assert_eq!(params["ce_price"], "12.5");
+        assert_eq!(params["ce_label"], "3,14");
+        assert_eq!(params["ce_currency"], "EUR");
+
+        // the locale setting takes precedence
+        let request = run(
+            track,
+            amounts(&event, &[("revenue", "1,234"), ("currency", " usd")]),
+            settings(&[("monetary_locale", "en-US")]),
+        )
+        .unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_revenue"], "1234");
+        assert_eq!(params["ce_currency"], "USD");
+
+        // invalid currencies and amounts
+        let request = run(
+            track,
+            amounts(&event, &[("revenue", "about 12"), ("currency", "dollars")]),
+            settings(&[]),
+        )
+        .unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_revenue"], "about 12");
+        assert_eq!(params.contains_key("ce_currency"), false);
+        assert_eq!(params["ce_original_currency"], "dollars");
+
+        // conversion to the base currency
+        let conversion = settings(&[
+            ("base_currency", "eur"),
+            ("currency_rates", "USD=0.9,GBP=1.15"),
+        ]);
+        let request = run(
+            track,
+            amounts(&event, &[("revenue", "$10.99 USD"), ("price", "3")]),
+            conversion.clone(),
+        )
+        .unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_revenue"], "9.89");
+        assert_eq!(params["ce_price"], "2.7");
+        assert_eq!(params["ce_currency"], "EUR");
+        assert_eq!(params["ce_original_currency"], "USD");
+
+        let request = run(
+            track,
+            amounts(&event, &[("revenue", "10"), ("currency", "CHF")]),
+            conversion.clone(),
+        )
+        .unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_revenue"], "10");
+        assert_eq!(params["ce_currency"], "CHF");
+        assert_eq!(params.contains_key("ce_original_currency"), false);
+
+        // ambiguous symbols are not a currency, the default currency is used instead
+        let mut with_default = conversion.clone();
+        with_default.push(("default_currency".to_string(), "GBP".to_string()));
+        for (amount, currency, converted) in [
+            ("$10", None, "11.5"),
+            ("C$10", None, "10"),
+            ("¥10", None, "11.5"),
+            ("£10", Some("GBP"), "11.5"),
+        ] {
+            let request = run(
+                track,
+                amounts(&event, &[("revenue", amount)]),
+                conversion.clone(),
+            )
+            .unwrap();
+            let params = query_params(&request.url);
+            assert_eq!(
+                params.get("ce_original_currency").map(String::as_str),
+                currency
+            );
+
+            let request = run(
+                track,
+                amounts(&event, &[("revenue", amount)]),
+                with_default.clone(),
+            )
+            .unwrap();
+            let params = query_params(&request.url);
+            assert_eq!(params["ce_revenue"], converted);
+        }
+
+        for invalid in [
+            ("base_currency", "euro"),
+            ("default_currency", "dollar"),
+            ("currency_rates", "USD=-1"),
+            ("currency_rates", "XYZ=1"),
+        ] {
+            assert_eq!(
+                run(track, event.clone(), settings(&[invalid])).is_err(),
+                true
+            );
+        }
+    }
+
+    #[test]
+    fn track_event_with_segment_ecommerce_preset_and_monetary_normalization() {
+        let mut event = sample_track_event(
+            "Product Added".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("event_preset".to_string(), "segment_ecommerce".to_string()),
+            ("monetary_properties".to_string(), "price,total".to_string()),
+        ];
+
+        // the preset renames "price", so "product_price" is normalized by the preset;
+        // "total" is listed in the monetary properties and only normalized there
+        for (amount, expected) in [
+            ("0,125", "0.125"),
+            ("1,234", "1.234"),
+            ("0.125", "0.125"),
+            ("1.234", "1234"),
+            ("12,50 €", "12.5"),
+        ] {
+            if let Data::Track(ref mut data) = event.data {
+                data.properties = vec![
+                    ("price".to_string(), amount.to_string()),
+                    ("total".to_string(), amount.to_string()),
+                ];
+            }
+            let request = run(track, event.clone(), settings.clone()).unwrap();
+            let params = query_params(&request.url);
+            assert_eq!(params["ce_product_price"], expected);
+            assert_eq!(params["ce_total"], expected);
+        }
+
+        // the same with an order event, where "price" is not renamed
+        if let Data::Track(ref mut data) = event.data {
+            data.name = "Order Completed".to_string();
+            data.properties = vec![("price".to_string(), "1,234".to_string())];
+        }
+        let request = run(track, event, settings).unwrap();
+        assert_eq!(query_params(&request.url)["ce_price"], "1.234");
+    }
+
+    #[test]
+    fn track_events_with_links_become_outgoing_and_download_events() {
+        let mut event = sample_track_event(
+            "Link Clicked".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        event.context.page.url = "https://www.example.com/pricing".to_string();
+        let link_event = |event: &Event, link: &str| {
+            let mut event = event.clone();
+            if let Data::Track(ref mut data) = event.data {
+                data.properties = vec![
+                    ("link_url".to_string(), link.to_string()),
+                    ("position".to_string(), "footer".to_string()),
+                ];
+            }
+            event
+        };
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("link_tracking".to_string(), "true".to_string()),
+        ];
+
+        let request = run(
+            track,
+            link_event(&event, "https://partner.com/offer"),
+            settings.clone(),
+        )
+        .unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["event"], "outgoing");
+        assert_eq!(params["ce_url"], "https://partner.com/offer");
+        assert_eq!(params["ce_position"], "footer");
+        assert_eq!(params.contains_key("ce_link_url"), false);
+
+        let request = run(
+            track,
+            link_event(&event, "/files/Report.PDF?v=2"),
+            settings.clone(),
+        )
+        .unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["event"], "download");
+        assert_eq!(params["ce_url"], "/files/Report.PDF?v=2");
+
+        // internal links are regular track events
+        for link in ["https://example.com/docs", "/docs/setup.html"] {
+            let request = run(track, link_event(&event, link), settings.clone()).unwrap();
+            let params = query_params(&request.url);
+            assert_eq!(params["event"], "Link Clicked");
+            assert_eq!(params["ce_link_url"], link);
+        }
+
+        let request = run(
+            track,
+            link_event(&event, "https://partner.com/offer"),
+            vec![("project_name".to_string(), "example.com".to_string())],
+        )
+        .unwrap();
+        assert_eq!(query_params(&request.url)["event"], "Link Clicked");
+
+        // custom property and extensions
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("link_tracking".to_string(), "true".to_string()),
+            ("link_url_property".to_string(), "href".to_string()),
+            ("download_extensions".to_string(), ".pdf,epub".to_string()),
+        ];
+        for (link, expected) in [
+            ("/assets/theme.css", "Link Clicked"),
+            ("https://www.example.com/book.EPUB", "download"),
+        ] {
+            let mut event = event.clone();
+            if let Data::Track(ref mut data) = event.data {
+                data.properties = vec![("href".to_string(), link.to_string())];
+            }
+            let request = run(track, event, settings.clone()).unwrap();
+            assert_eq!(query_params(&request.url)["event"], expected);
+        }
+    }
+
+    #[test]
+    fn link_url_collides_with_an_existing_url_property() {
+        let mut event = sample_track_event(
+            "Link Clicked".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        event.context.page.url = "https://www.example.com/pricing".to_string();
+        if let Data::Track(ref mut data) = event.data {
+            data.properties = vec![
+                (
+                    "url".to_string(),
+                    "https://www.example.com/pricing".to_string(),
+                ),
+                (
+                    "link_url".to_string(),
+                    "https://partner.com/offer".to_string(),
+                ),
+            ];
+        }
+        let settings = |policy: &str| {
+            vec![
+                ("project_name".to_string(), "example.com".to_string()),
+                ("link_tracking".to_string(), "true".to_string()),
+                ("property_collision_policy".to_string(), policy.to_string()),
+            ]
+        };
+
+        // the link is set last
+        let request = run(track, event.clone(), settings("explicit_prefix_wins")).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["event"], "outgoing");
+        assert_eq!(params["ce_url"], "https://partner.com/offer");
+        assert_eq!(params["ce_property_collisions"], "ce_url");
+
+        let request = run(track, event.clone(), settings("first_wins")).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_url"], "https://www.example.com/pricing");
+        assert_eq!(params["ce_property_collisions"], "ce_url");
+
+        let request = run(track, event, settings("rename")).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_url"], "https://www.example.com/pricing");
+        assert_eq!(params["ce_url_2"], "https://partner.com/offer");
+    }
+
+    #[test]
+    fn track_event_with_scope_routing() {
+        let mut event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        if let Data::Track(ref mut data) = event.data {
+            data.properties = vec![
+                ("visitor.plan".to_string(), "pro".to_string()),
+                ("session.channel".to_string(), "ads".to_string()),
+                ("event.cv_like".to_string(), "yes".to_string()),
+                ("ab_variant".to_string(), "B".to_string()),
+                ("visitor.".to_string(), "empty".to_string()),
+            ];
+        }
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            (
+                "property_scopes".to_string(),
+                "ab_variant=visitor".to_string(),
+            ),
+        ];
+        let result = run(track, event.clone(), settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["cv_plan"], "pro");
+        assert_eq!(params["cs_channel"], "ads");
+        assert_eq!(params["ce_cv_like"], "yes");
+        assert_eq!(params["cv_ab_variant"], "B");
+        assert_eq!(params["ce_visitor."], "empty");
+        assert_eq!(params.contains_key("ce_visitor.plan"), false);
+        assert_eq!(params.contains_key("ce_ab_variant"), false);
+
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            (
+                "property_scopes".to_string(),
+                "ab_variant=account".to_string(),
+            ),
+        ];
+        assert_eq!(run(track, event, settings).is_err(), true);
+    }
+
+    #[test]
+    fn user_event_with_id_property_collision() {
+        let mut event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let mut data = sample_user_data("abc".to_string());
+        data.properties = vec![("id".to_string(), "property id".to_string())];
+        event.data = Data::User(data);
+        let mut settings = vec![("project_name".to_string(), "example.com".to_string())];
+
+        // the user id is the identifier, the property collides with it
+        let request = run(user, event.clone(), settings.clone()).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(request.url.matches("cv_id=").count(), 1);
+        assert_eq!(params["cv_id"], "123");
+        assert_eq!(params["cv_property_collisions"], "cv_id");
+
+        settings.push((
+            "property_collision_policy".to_string(),
+            "rename".to_string(),
+        ));
+        let request = run(user, event.clone(), settings.clone()).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["cv_id"], "123");
+        assert_eq!(params["cv_id_2"], "property id");
+        assert_eq!(params["cv_property_collisions"], "cv_id");
+
+        // without any id, the property is the identifier
+        event.context.user = UserData::default();
+        if let Data::User(ref mut data) = event.data {
+            data.user_id = String::new();
+            data.anonymous_id = String::new();
+        }
+        let request = run(user, event, settings).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(request.url.matches("cv_id=").count(), 1);
+        assert_eq!(params["cv_id"], "property id");
+        assert_eq!(params.contains_key("cv_property_collisions"), false);
+    }
+
+    #[test]
+    fn track_event_with_property_collision_policies() {
+        let mut event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        if let Data::Track(ref mut data) = event.data {
+            data.properties = vec![
+                ("ce_plan".to_string(), "pro".to_string()),
+                ("plan".to_string(), "free".to_string()),
+                ("plan_2".to_string(), "trial".to_string()),
+            ];
+        }
+        let settings = |policy: &str| {
+            vec![
+                ("project_name".to_string(), "example.com".to_string()),
+                ("property_collision_policy".to_string(), policy.to_string()),
+            ]
+        };
+
+        let request = run(track, event.clone(), settings("explicit_prefix_wins")).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_plan"], "pro");
+        assert_eq!(params["ce_property_collisions"], "ce_plan");
+
+        let request = run(track, event.clone(), settings("")).unwrap();
+        assert_eq!(query_params(&request.url)["ce_plan"], "pro");
+
+        let mut unprefixed_first = event.clone();
+        if let Data::Track(ref mut data) = unprefixed_first.data {
+            data.properties.swap(0, 1);
+        }
+        let request = run(track, unprefixed_first, settings("first_wins")).unwrap();
+        assert_eq!(query_params(&request.url)["ce_plan"], "free");
+
+        // renamed keys skip the names already in use
+        let request = run(track, event.clone(), settings("rename")).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_plan"], "pro");
+        assert_eq!(params["ce_plan_2"], "trial");
+        assert_eq!(params["ce_plan_3"], "free");
+        assert_eq!(params["ce_property_collisions"], "ce_plan");
+
+        assert_eq!(run(track, event, settings("last_wins")).is_err(), true);
+    }
+
+    #[test]
+    fn track_event_with_property_set_twice() {
+        let mut event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        if let Data::Track(ref mut data) = event.data {
+            data.properties = vec![
+                ("title".to_string(), "checkout".to_string()),
+                ("visitor.prop1".to_string(), "value2".to_string()),
+                ("visitor.country".to_string(), "FR".to_string()),
+            ];
+        }
+        let settings = |policy: &str| {
+            vec![
+                ("project_name".to_string(), "example.com".to_string()),
+                ("property_collision_policy".to_string(), policy.to_string()),
+            ]
+        };
+
+        // the values set last replace the page title and the context user property,
+        // a value identical to the one already set is not a collision
+        let request = run(track, event.clone(), settings("explicit_prefix_wins")).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_title"], "checkout");
+        assert_eq!(params["cv_prop1"], "value2");
+        assert_eq!(params["cv_country"], "FR");
+        assert_eq!(params["ce_property_collisions"], "cv_prop1,ce_title");
+
+        let request = run(track, event.clone(), settings("first_wins")).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_title"], "page title");
+        assert_eq!(params["cv_prop1"], "value1");
+
+        let request = run(track, event, settings("rename")).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["ce_title"], "page title");
+        assert_eq!(params["ce_title_2"], "checkout");
+        assert_eq!(params["cv_prop1_2"], "value2");
+    }
+
+    #[test]
+    fn user_event_with_property_collisions() {
+        let mut event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let mut data = sample_user_data("abc".to_string());
+        data.properties = vec![
+            ("email".to_string(), "jane@example.com".to_string()),
+            ("cv_email".to_string(), "john@example.com".to_string()),
+        ];
+        event.data = Data::User(data);
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            (
+                "property_collision_policy".to_string(),
+                "first_wins".to_string(),
+            ),
+        ];
+
+        let request = run(user, event, settings).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params["cv_email"], "jane@example.com");
+        assert_eq!(params["cv_property_collisions"], "cv_email");
+    }
+
+    #[test]
+    fn page_event_with_url_canonicalization() {
+        let mut event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        if let Data::Page(ref mut data) = event.data {
+            data.url =
+                "https://WWW.Example.com/Path?token=secret&utm_source=x&id=1#section".to_string();
+            data.referrer = "https://Other.com/?UTM_medium=y#top".to_string();
+        }
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            (
+                "url_strip_query_params".to_string(),
+                "token, utm_*".to_string(),
+            ),
+            ("url_strip_fragment".to_string(), "true".to_string()),
+            ("url_lowercase_host".to_string(), "true".to_string()),
+        ];
+        let result = run(page, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["ce_uri"], "https://www.example.com/Path?id=1");
+        assert_eq!(params["referer"], "https://other.com/");
+    }
+
+    #[test]
+    fn track_event_with_allowed_query_params() {
+        let mut event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        event.context.page.url =
+            "https://example.com/full-url?test=1&email=a%40b.com&page=2#anchor".to_string();
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("url_allowed_query_params".to_string(), "page".to_string()),
+            ("url_strip_query_params".to_string(), "page".to_string()), // ignored
+        ];
+        let result = run(track, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(
+            params["ce_uri"],
+            "https://example.com/full-url?page=2#anchor"
+        );
+        assert_eq!(params["referer"], "https://example.com/another-page");
+    }
+
+    #[test]
+    fn page_event_without_url_canonicalization() {
+        let event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![("project_name".to_string(), "example.com".to_string())];
+        let result = run(page, event, settings);
+
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["ce_uri"], "https://example.com/full-url?test=1");
+    }
+
+    #[test]
+    fn invalid_boolean_setting() {
+        let event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("url_strip_fragment".to_string(), "yes please".to_string()),
+        ];
+        let result = run(page, event, settings);
+
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[test]
+    fn track_event_with_pii_redaction() {
+        let mut event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        event.context.page.title = "Welcome back john.doe@example.com".to_string();
+        event.context.user.properties = vec![
+            ("email".to_string(), "john.doe@example.com".to_string()),
+            ("phone".to_string(), "+33 6 12 34 56 78".to_string()),
+        ];
+        if let Data::Track(ref mut data) = event.data {
+            data.properties = vec![
+                ("card".to_string(), "card 4111 1111 1111 1111".to_string()),
+                ("date".to_string(), "2024-01-15 10:30".to_string()),
+                ("order".to_string(), "1234567890123".to_string()),
+            ];
+        }
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("pii_action".to_string(), "redact".to_string()),
+            ("pii_exempt_fields".to_string(), "cv_email".to_string()),
+        ];
+        let result = run(track, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["ce_title"], "Welcome back [REDACTED]");
+        assert_eq!(params["ce_card"], "card [REDACTED]");
+        assert_eq!(params["ce_date"], "2024-01-15 10:30");
+        assert_eq!(params["ce_order"], "1234567890123"); // not a valid card number
+        assert_eq!(params["cv_email"], "john.doe@example.com"); // exempted
+        assert_eq!(params["cv_phone"], "[REDACTED]");
+        assert_eq!(params["ip"], "192.168.0.1");
+        assert_eq!(params["project"], "example.com");
+    }
+
+    #[test]
+    fn track_event_with_percent_encoded_pii_in_urls() {
+        let mut event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        event.context.page.url =
+            "https://ex.com/a%20b?email=jane%40example.com&tel=%2B33%206%2012%2034%2056%2078&q=x%26y"
+                .to_string();
+        event.context.page.referrer = "https://ex.com/jane%40example.com/".to_string();
+        let settings = |action: &str| {
+            vec![
+                ("project_name".to_string(), "example.com".to_string()),
+                ("pii_action".to_string(), action.to_string()),
+            ]
+        };
+
+        // only the components holding PII are re-encoded
+        let params = query_params(&run(track, event.clone(), settings("redact")).unwrap().url);
+        assert_eq!(
+            params["ce_uri"],
+            "https://ex.com/a%20b?email=%5BREDACTED%5D&tel=%5BREDACTED%5D&q=x%26y"
+        );
+        assert_eq!(params["referer"], "https://ex.com/%5BREDACTED%5D/");
+
+        let params = query_params(&run(track, event.clone(), settings("hash")).unwrap().url);
+        assert_eq!(
+            params["referer"],
+            format!("https://ex.com/{}/", pii::sha256_hex("jane@example.com"))
+        );
+
+        let params = query_params(&run(track, event, settings("drop")).unwrap().url);
+        assert_eq!(params.contains_key("ce_uri"), false);
+        assert_eq!(params.contains_key("referer"), false);
+        assert_eq!(params["ce_title"], "page title");
+    }
+
+    #[test]
+    fn page_event_with_pii_hashing_and_drop() {
+        let mut event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        if let Data::Page(ref mut data) = event.data {
+            data.title = "Contact (555) 123-4567".to_string();
+            data.url = "https://example.com/?email=jane@example.com".to_string();
+        }
+
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("pii_action".to_string(), "hash".to_string()),
+            ("pii_detectors".to_string(), "email".to_string()),
+        ];
+        let result = run(page, event.clone(), settings);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(
+            params["ce_uri"],
+            format!(
+                "https://example.com/?email={}",
+                pii::sha256_hex("jane@example.com")
+            )
+        );
+        assert_eq!(params["ce_title"], "Contact (555) 123-4567"); // phone detector disabled
+
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("pii_action".to_string(), "drop".to_string()),
+        ];
+        let result = run(page, event, settings);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params.contains_key("ce_uri"), false);
+        assert_eq!(params.contains_key("ce_title"), false);
+        assert_eq!(params["ce_page_prop1"], "value1");
+    }
+
+    #[test]
+    fn user_event_with_pii_redaction() {
+        let mut event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        event.data = Data::User(UserData {
+            user_id: "jane@example.com".to_string(),
+            anonymous_id: "456".to_string(),
+            edgee_id: "abc".to_string(),
+            properties: vec![
+                ("email".to_string(), "jane@example.com".to_string()),
+                ("bio".to_string(), "call me at 555-123-4567".to_string()),
+            ],
+        });
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("pii_action".to_string(), "redact".to_string()),
+            (
+                "pii_exempt_fields".to_string(),
+                "cv_email, cv_id".to_string(),
+            ),
+        ];
+        let result = run(user, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["cv_id"], "jane@example.com");
+        assert_eq!(params["cv_email"], "jane@example.com");
+        assert_eq!(params["cv_bio"], "call me at [REDACTED]");
+    }
+
+    #[test]
+    fn invalid_pii_action_setting() {
+        let event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("pii_action".to_string(), "encrypt".to_string()),
+        ];
+        let result = run(user, event, settings);
+
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[test]
+    fn user_ids_are_hashed_consistently() {
+        let track_event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let mut user_event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        user_event.data = Data::User(sample_user_data("abc".to_string()));
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("user_id_hashing_key".to_string(), "secret".to_string()),
+            ("hashed_user_properties".to_string(), "prop1".to_string()),
+        ];
+
+        let track_params = query_params(&run(track, track_event, settings.clone()).unwrap().url);
+        let user_params = query_params(&run(user, user_event, settings).unwrap().url);
+
+        assert_eq!(track_params["cv_user_id"].len(), 64);
+        assert_eq!(track_params["cv_user_id"] == "123", false);
+        assert_eq!(track_params["cv_user_id"], user_params["cv_id"]);
+        assert_eq!(track_params["cv_anonymous_id"] == "456", false);
+        assert_eq!(track_params["cv_prop1"], user_params["cv_prop1"]);
+        assert_eq!(track_params["cv_prop1"] == "value1", false);
+        assert_eq!(track_params["cv_prop2"], "10");
+        assert_eq!(user_params["cv_prop2"], "10");
+    }
+
+    #[test]
+    fn user_ids_depend_on_hashing_key() {
+        let event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = |key: &str| {
+            vec![
+                ("project_name".to_string(), "example.com".to_string()),
+                ("user_id_hashing_key".to_string(), key.to_string()),
+            ]
+        };
+
+        let first = query_params(&run(user, event.clone(), settings("first")).unwrap().url);
+        let second = query_params(&run(user, event.clone(), settings("second")).unwrap().url);
+        let disabled = query_params(&run(user, event, settings("")).unwrap().url);
+
+        assert_eq!(first["cv_id"] == second["cv_id"], false);
+        assert_eq!(disabled["cv_id"], "123");
+    }
+
+    #[test]
+    fn track_event_with_default_properties() {
+        let event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            (
+                "default_visitor_properties".to_string(),
+                "environment=staging, prop1=default".to_string(),
+            ),
+            (
+                "default_event_properties".to_string(),
+                "app_version=1.2.3,ce_currency=EUR".to_string(),
+            ),
+            (
+                "default_session_properties".to_string(),
+                "site_section=blog".to_string(),
+            ),
+        ];
+        let result = run(track, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["cv_environment"], "staging");
+        assert_eq!(params["cv_prop1"], "value1"); // event value wins
+        assert_eq!(params["ce_app_version"], "1.2.3");
+        assert_eq!(params["ce_currency"], "USD"); // event value wins
+        assert_eq!(params["cs_site_section"], "blog");
+    }
+
+    #[test]
+    fn user_event_with_default_properties() {
+        let event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            (
+                "default_visitor_properties".to_string(),
+                "environment=staging".to_string(),
+            ),
+            (
+                "default_event_properties".to_string(),
+                "app_version=1.2.3".to_string(),
+            ),
+        ];
+        let result = run(user, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["cv_environment"], "staging");
+        assert_eq!(params.contains_key("ce_app_version"), false);
+    }
+
+    #[test]
+    fn invalid_default_properties_setting() {
+        let event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            (
+                "default_event_properties".to_string(),
+                "environment".to_string(),
+            ),
+        ];
+        let result = run(page, event, settings);
+
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[test]
+    fn disabled_event_types_are_not_forwarded() {
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("edgee_page_event_enabled".to_string(), "false".to_string()),
+            ("edgee_track_event_enabled".to_string(), "false".to_string()),
+            ("edgee_user_event_enabled".to_string(), "false".to_string()),
+        ];
+        let page = run(
+            page,
+            sample_page_event(
+                Some(Consent::Granted),
+                "abc".to_string(),
+                "fr".to_string(),
+                true,
+            ),
+            settings.clone(),
+        );
+        let track = run(
+            track,
+            sample_track_event(
+                "test_event".to_string(),
+                Some(Consent::Granted),
+                "abc".to_string(),
+                "fr".to_string(),
+                true,
+            ),
+            settings.clone(),
+        );
+        let user = run(
+            user,
+            sample_user_event(
+                Some(Consent::Granted),
+                "abc".to_string(),
+                "fr".to_string(),
+                true,
+            ),
+            settings,
+        );
+
+        assert_eq!(page.unwrap_err(), "Page events are disabled");
+        assert_eq!(track.unwrap_err(), "Track events are disabled");
+        assert_eq!(user.unwrap_err(), "User events are disabled");
+    }
+
+    #[test]
+    fn enabled_event_types_are_forwarded() {
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("edgee_page_event_enabled".to_string(), "false".to_string()),
+            ("edgee_track_event_enabled".to_string(), "true".to_string()),
+        ];
+        let track = run(
+            track,
+            sample_track_event(
+                "test_event".to_string(),
+                Some(Consent::Granted),
+                "abc".to_string(),
+                "fr".to_string(),
+                true,
+            ),
+            settings.clone(),
+        );
+        let user = run(
+            user,
+            sample_user_event(
+                Some(Consent::Granted),
+                "abc".to_string(),
+                "fr".to_string(),
+                true,
+            ),
+            settings,
+        );
+
+        assert_eq!(track.is_err(), false);
+        assert_eq!(user.is_err(), false);
+    }
+
+    #[test]
+    fn page_event_restricted_to_hosts() {
+        let settings = |hosts: &str| {
+            vec![
+                ("project_name".to_string(), "example.com".to_string()),
+                ("page_event_hosts".to_string(), hosts.to_string()),
+            ]
+        };
+        let event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+
+        assert_eq!(
+            run(page, event.clone(), settings("Example.com")).is_err(),
+            false
+        );
+        assert_eq!(
+            run(page, event.clone(), settings("other.com, *.example.com")).is_err(),
+            false
+        );
+        assert_eq!(
+            run(page, event.clone(), settings("www.example.com")).unwrap_err(),
+            "Page events are disabled for this host: example.com"
+        );
+
+        let mut event = event;
+        if let Data::Page(ref mut data) = event.data {
+            data.url = "https://shop.example.com:8080/cart".to_string();
+        }
+        assert_eq!(
+            run(page, event.clone(), settings("*.example.com")).is_err(),
+            false
+        );
+        assert_eq!(
+            run(page, event.clone(), settings("example.com")).unwrap_err(),
+            "Page events are disabled for this host: shop.example.com"
+        );
+
+        if let Data::Page(ref mut data) = event.data {
+            data.url = "/cart?email=jane@example.com".to_string();
+        }
+        assert_eq!(
+            run(page, event, settings("example.com")).unwrap_err(),
+            "Page events are disabled for pages without a host"
+        );
+    }
+
+    #[test]
+    fn track_event_with_idle_timeout() {
+        let mut event = sample_track_event(
+            "video_play".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("idle_timeout".to_string(), "60000".to_string()),
+        ];
+
+        let params = query_params(&run(track, event.clone(), settings.clone()).unwrap().url);
+        assert_eq!(params["timeout"], "60000");
+
+        if let Data::Track(ref mut data) = event.data {
+            data.properties
+                .push(("woopra_timeout".to_string(), "900000".to_string()));
+        }
+        let params = query_params(&run(track, event.clone(), settings.clone()).unwrap().url);
+        assert_eq!(params["timeout"], "900000");
+        assert_eq!(params.contains_key("ce_woopra_timeout"), false);
+
+        if let Data::Track(ref mut data) = event.data {
+            data.properties.pop();
+            data.properties
+                .push(("woopra_timeout".to_string(), "soon".to_string()));
+        }
+        let params = query_params(&run(track, event, settings).unwrap().url);
+        assert_eq!(params["timeout"], "60000");
+    }
+
+    #[test]
+    fn page_event_with_idle_timeout_override() {
+        let mut event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![("project_name".to_string(), "example.com".to_string())];
+
+        let params = query_params(&run(page, event.clone(), settings.clone()).unwrap().url);
+        assert_eq!(params.contains_key("timeout"), false);
+
+        if let Data::Page(ref mut data) = event.data {
+            data.properties
+                .push(("woopra_timeout".to_string(), "600000".to_string()));
+        }
+        let params = query_params(&run(page, event, settings).unwrap().url);
+        assert_eq!(params["timeout"], "600000");
+        assert_eq!(params.contains_key("ce_page_woopra_timeout"), false);
+    }
+
+    #[test]
+    fn invalid_idle_timeout_setting() {
+        let event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("idle_timeout".to_string(), "-1".to_string()),
+        ];
+
+        assert_eq!(run(page, event, settings).is_err(), true);
+    }
+
+    #[test]
+    fn track_event_with_custom_app_and_component_metadata() {
+        let mut event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        // the metadata keys belong to the mapping, whatever the collision policy
+        if let Data::Track(ref mut data) = event.data {
+            data.properties = vec![
+                ("edgee_component_version".to_string(), "0.0.1".to_string()),
+                (
+                    "ce_edgee_component_version".to_string(),
+                    "0.0.2".to_string(),
+                ),
+            ];
+        }
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("app_name".to_string(), "brand-a".to_string()),
+            ("send_component_metadata".to_string(), "true".to_string()),
+        ];
+        for policy in ["explicit_prefix_wins", "first_wins", "rename"] {
+            let mut settings = settings.clone();
+            settings.push(("property_collision_policy".to_string(), policy.to_string()));
+            let result = run(track, event.clone(), settings);
+
+            assert_eq!(result.is_err(), false);
+            let params = query_params(&result.unwrap().url);
+            assert_eq!(params["app"], "brand-a");
+            assert_eq!(
+                params["ce_edgee_component_version"],
+                env!("CARGO_PKG_VERSION")
+            );
+            assert_eq!(params.contains_key("ce_edgee_component_version_2"), false);
+            assert_eq!(params.contains_key("ce_property_collisions"), false);
+        }
+    }
+
+    #[test]
+    fn page_event_with_default_app() {
+        let event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![("project_name".to_string(), "example.com".to_string())];
+        let result = run(page, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let params = query_params(&result.unwrap().url);
+        assert_eq!(params["app"], "Edgee");
+        assert_eq!(params.contains_key("ce_edgee_component_version"), false);
+    }
+
+    #[test]
+    fn events_are_routed_to_projects_by_hostname() {
+        let settings = vec![
+            ("project_name".to_string(), "default.com".to_string()),
+            (
+                "project_routes".to_string(),
+                "example.com=brand-a.com, *.brand-b.com=brand-b.com".to_string(),
+            ),
+        ];
+        let page_event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let mut track_event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        track_event.context.page.url = "https://shop.brand-b.com/cart".to_string();
+        let mut user_event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        user_event.context.page.url = "https://unknown.com/".to_string();
+
+        let page = query_params(&run(page, page_event, settings.clone()).unwrap().url);
+        let track = query_params(&run(track, track_event, settings.clone()).unwrap().url);
+        let user = query_params(&run(user, user_event, settings).unwrap().url);
+
+        assert_eq!(page["project"], "brand-a.com");
+        assert_eq!(track["project"], "brand-b.com");
+        assert_eq!(user["project"], "default.com");
+    }
+
+    #[test]
+    fn events_are_routed_to_projects_by_property() {
+        let settings = vec![
+            ("project_name".to_string(), "default.com".to_string()),
+            (
+                "project_routes".to_string(),
+                "brand-a=brand-a.com,brand-b=brand-b.com,example.com=example.com".to_string(),
+            ),
+            ("project_routing_property".to_string(), "brand".to_string()),
+        ];
+        let mut track_event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        if let Data::Track(ref mut data) = track_event.data {
+            data.properties
+                .push(("brand".to_string(), "brand-b".to_string()));
+        }
+        let mut user_event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        user_event
+            .context
+            .user
+            .properties
+            .push(("brand".to_string(), "brand-a".to_string()));
+        let page_event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+
+        let track = query_params(&run(track, track_event, settings.clone()).unwrap().url);
+        let user = query_params(&run(user, user_event, settings.clone()).unwrap().url);
+        let page = query_params(&run(page, page_event, settings).unwrap().url);
+
+        assert_eq!(track["project"], "brand-b.com");
+        assert_eq!(user["project"], "brand-a.com");
+        assert_eq!(page["project"], "example.com"); // no property, falls back to the hostname
+    }
+
+    #[test]
+    fn mirror_mode_sends_identical_payloads_to_each_project() {
+        let event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            (
+                "mirror_projects".to_string(),
+                "sandbox.example.com".to_string(),
+            ),
+            (
+                "mirror_fanout_endpoint".to_string(),
+                "https://proxy.example.com/fanout".to_string(),
+            ),
+        ];
+        let result = run(track, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let edgee_request = result.unwrap();
+        assert_eq!(edgee_request.method, HttpMethod::Post);
+        assert_eq!(edgee_request.url, "https://proxy.example.com/fanout");
+
+        let urls: Vec<&str> = edgee_request.body.lines().collect();
+        assert_eq!(urls.len(), 2);
+        assert_eq!(
+            urls[0].starts_with("https://www.woopra.com/track/ce?"),
+            true
+        );
+        assert_eq!(
+            urls[1].starts_with("https://www.woopra.com/track/ce?"),
+            true
+        );
+
+        let mut primary = query_params(urls[0]);
+        let mut mirror = query_params(urls[1]);
+        assert_eq!(primary.remove("project").unwrap(), "example.com");
+        assert_eq!(mirror.remove("project").unwrap(), "sandbox.example.com");
+        assert_eq!(primary, mirror);
+    }
+
+    #[test]
+    fn mirror_mode_for_user_events() {
+        let event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            (
+                "mirror_projects".to_string(),
+                "sandbox-1.com,sandbox-2.com".to_string(),
+            ),
+            (
+                "mirror_fanout_endpoint".to_string(),
+                "https://proxy.example.com/fanout".to_string(),
+            ),
+        ];
+        let edgee_request = run(user, event, settings).unwrap();
+
+        let urls: Vec<&str> = edgee_request.body.lines().collect();
+        assert_eq!(urls.len(), 3);
+        let projects: Vec<String> = urls
+            .iter()
+            .map(|url| query_params(url)["project"].clone())
+            .collect();
+        assert_eq!(
+            projects,
+            vec!["example.com", "sandbox-1.com", "sandbox-2.com"]
+        );
+        assert_eq!(
+            urls.iter()
+                .all(|url| url.starts_with("https://www.woopra.com/track/identify?")),
+            true
+        );
+    }
+
+    #[test]
+    fn mirror_mode_requires_fanout_endpoint() {
+        let event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            (
+                "mirror_projects".to_string(),
+                "sandbox.example.com".to_string(),
+            ),
+        ];
+
+        assert_eq!(
+            run(page, event, settings).unwrap_err(),
+            "Missing mirror_fanout_endpoint setting"
+        );
+    }
+
+    #[test]
+    fn sampling_keeps_or_drops_whole_visitor_journeys() {
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("sample_rate".to_string(), "0.5".to_string()),
+        ];
+        let mut kept = 0;
+        for index in 0..200 {
+            let edgee_id = format!("visitor-{index}");
+            let page_event = sample_page_event(
+                Some(Consent::Granted),
+                edgee_id.clone(),
+                "fr".to_string(),
+                true,
+            );
+            let track_event = sample_track_event(
+                "test_event".to_string(),
+                Some(Consent::Granted),
+                edgee_id.clone(),
+                "fr".to_string(),
+                true,
+            );
+            let mut user_event =
+                sample_user_event(Some(Consent::Granted), edgee_id, "fr".to_string(), true);
+            // the user data of identify events is not used for bucketing
+            if let Data::User(ref mut data) = user_event.data {
+                data.user_id = format!("user-{index}");
+                data.edgee_id = String::new();
+            }
+
+            let page = run(page, page_event, settings.clone());
+            let track = run(track, track_event, settings.clone());
+            let user = run(user, user_event, settings.clone());
+
+            assert_eq!(page.is_ok(), track.is_ok());
+            assert_eq!(page.is_ok(), user.is_ok());
+            if let (Ok(track), Ok(user)) = (track, user) {
+                kept += 1;
+                assert_eq!(query_params(&track.url)["ce_sample_rate"], "0.5");
+                assert_eq!(query_params(&user.url)["cv_sample_rate"], "0.5");
+            } else {
+                assert_eq!(page.unwrap_err(), "Event dropped by sampling");
+            }
+        }
+        assert_eq!((60..140).contains(&kept), true);
+    }
+
+    #[test]
+    fn sampling_keeps_the_same_visitors_after_login() {
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("sample_rate".to_string(), "0.5".to_string()),
+        ];
+        let mut kept = 0;
+        for index in 0..100 {
+            let mut before_login = sample_track_event(
+                "test_event".to_string(),
+                Some(Consent::Granted),
+                format!("visitor-{index}"),
+                "fr".to_string(),
+                true,
+            );
+            before_login.context.user.user_id = String::new();
+            before_login.context.user.anonymous_id = format!("anonymous-{index}");
+            let mut after_login = before_login.clone();
+            after_login.context.user.user_id = format!("user-{index}");
+
+            let before = run(track, before_login.clone(), settings.clone());
+            let after = run(track, after_login.clone(), settings.clone());
+            assert_eq!(before.is_ok(), after.is_ok());
+            if before.is_ok() {
+                kept += 1;
+            }
+
+            // without device id, the anonymous id is used
+            before_login.context.user.edgee_id = String::new();
+            after_login.context.user.edgee_id = String::new();
+            assert_eq!(
+                run(track, before_login, settings.clone()).is_ok(),
+                run(track, after_login, settings.clone()).is_ok()
+            );
+        }
+        assert_eq!((30..70).contains(&kept), true);
+    }
+
+    #[test]
+    fn sampling_rate_bounds() {
+        let event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = |rate: &str| {
+            vec![
+                ("project_name".to_string(), "example.com".to_string()),
+                ("sample_rate".to_string(), rate.to_string()),
+            ]
+        };
+
+        assert_eq!(
+            run(track, event.clone(), settings("0")).unwrap_err(),
+            "Event dropped by sampling"
+        );
+        let params = query_params(&run(track, event.clone(), settings("1")).unwrap().url);
+        assert_eq!(params.contains_key("ce_sample_rate"), false);
+        assert_eq!(run(track, event, settings("1.5")).is_err(), true);
+
+        let user_event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let params = query_params(&run(user, user_event, settings("1")).unwrap().url);
+        assert_eq!(params.contains_key("cv_sample_rate"), false);
+    }
+
+    #[test]
+    fn sample_rate_tag_is_owned_by_the_mapping() {
+        let mut event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        if let Data::Track(ref mut data) = event.data {
+            data.properties = vec![
+                ("sample_rate".to_string(), "1".to_string()),
+                ("ce_sample_rate".to_string(), "0.1".to_string()),
+            ];
+        }
+        for policy in ["explicit_prefix_wins", "first_wins", "rename"] {
+            let settings = vec![
+                ("project_name".to_string(), "example.com".to_string()),
+                ("sample_rate".to_string(), "0.99".to_string()),
+                ("property_collision_policy".to_string(), policy.to_string()),
+            ];
+            let params = query_params(&run(track, event.clone(), settings).unwrap().url);
+            assert_eq!(params["ce_sample_rate"], "0.99");
+            assert_eq!(params.contains_key("ce_sample_rate_2"), false);
+            assert_eq!(params.contains_key("ce_property_collisions"), false);
+        }
+    }
+
+    #[test]
+    fn debug_mode_sends_payload_dump_to_echo_endpoint() {
+        let event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("debug_mode".to_string(), "true".to_string()),
+            (
+                "debug_endpoint".to_string(),
+                "https://echo.example.com/".to_string(),
+            ),
+        ];
+        let result = run(track, event, settings);
+
+        assert_eq!(result.is_err(), false);
+        let edgee_request = result.unwrap();
+        assert_eq!(edgee_request.method, HttpMethod::Post);
+        assert_eq!(
+            edgee_request
+                .url
+                .starts_with("https://echo.example.com/track/ce?"),
+            true
+        );
+        assert_eq!(edgee_request.forward_client_headers, false);
+        assert_eq!(
+            edgee_request
+                .body
+                .starts_with("GET https://www.woopra.com/track/ce?"),
+            true
+        );
+        let dump: HashMap<String, String> = edgee_request
+            .body
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let (name, value) = line.split_once(" = ").unwrap();
+                (name.trim_end().to_string(), value.to_string())
+            })
+            .collect();
+        assert_eq!(dump, query_params(&edgee_request.url));
+        assert_eq!(dump["ce_prop1"], "value1");
+        assert_eq!(dump["event"], "test_event");
+    }
+
+    #[test]
+    fn debug_mode_lists_mirrored_requests() {
+        let event = sample_track_event(
+            "test_event".to_string(),
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("debug_mode".to_string(), "true".to_string()),
+            (
+                "debug_endpoint".to_string(),
+                "https://echo.example.com".to_string(),
+            ),
+            (
+                "mirror_projects".to_string(),
+                "sandbox.example.com".to_string(),
+            ),
+            (
+                "mirror_fanout_endpoint".to_string(),
+                "https://fanout.example.com/woopra".to_string(),
+            ),
+        ];
+        let edgee_request = run(track, event, settings).unwrap();
+
+        // the request goes to the echo endpoint only, the mirrored URL is listed in the dump
+        assert_eq!(
+            edgee_request
+                .url
+                .starts_with("https://echo.example.com/track/ce?"),
+            true
+        );
+        assert_eq!(query_params(&edgee_request.url)["project"], "example.com");
+        let (_, mirrored) = edgee_request.body.split_once("# mirrored to\n").unwrap();
+        let mirrored: Vec<&str> = mirrored.lines().collect();
+        assert_eq!(mirrored.len(), 1);
+        let url = mirrored[0].strip_prefix("GET ").unwrap();
+        assert_eq!(url.starts_with("https://www.woopra.com/track/ce?"), true);
+        assert_eq!(query_params(url)["project"], "sandbox.example.com");
+    }
+
+    #[test]
+    fn debug_mode_requires_endpoint() {
+        let event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![
+            ("project_name".to_string(), "example.com".to_string()),
+            ("debug_mode".to_string(), "true".to_string()),
+        ];
+
+        assert_eq!(
+            run(user, event, settings).unwrap_err(),
+            "Missing debug_endpoint setting"
+        );
+    }
+
+    #[test]
+    fn json_events_are_mapped_by_data_type() {
+        let event: Event = serde_json::from_str(
+            r#"{
+                "uuid": "abc",
+                "type": "track",
+                "data": {"name": "Order", "properties": {"price": 9.5, "paid": true, "id": 3}},
+                "context": {"user": {"user_id": "123"}, "client": {"locale": "fr"}},
+                "consent": "granted"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(event.event_type, EventType::Track);
+        assert_eq!(event.consent, Some(Consent::Granted));
+
+        let settings = Settings::new(vec![(
+            "project_name".to_string(),
+            "example.com".to_string(),
+        )])
+        .unwrap();
+        let request = build_request(&event, &settings).unwrap();
+        let params = query_params(&request.url);
+        assert_eq!(params.get("event").unwrap(), "Order");
+        assert_eq!(params.get("ce_price").unwrap(), "9.5");
+        assert_eq!(params.get("ce_paid").unwrap(), "true");
+        assert_eq!(params.get("ce_id").unwrap(), "3");
+        assert_eq!(params.get("cv_user_id").unwrap(), "123");
+        assert_eq!(params.get("language").unwrap(), "fr");
+    }
+
+    #[test]
+    fn json_properties_keep_their_order_and_accept_any_value() {
+        let event: Event = serde_json::from_str(
+            r#"{
+                "type": "track",
+                "data": {
+                    "name": "Order",
+                    "properties": {
+                        "zone": "eu",
+                        "coupon": null,
+                        "tags": ["a", "b"],
+                        "shipping": {"method": "express", "days": 2},
+                        "amount": 1.0,
+                        "ce_zone": "us"
+                    },
+                    "products": [{"sku": "A1", "size": null}]
+                }
+            }"#,
+        )
+        .unwrap();
+        let Data::Track(ref data) = event.data else {
+            panic!("expected track data");
+        };
+        assert_eq!(
+            data.properties,
+            vec![
+                ("zone".to_string(), "eu".to_string()),
+                ("tags".to_string(), r#"["a","b"]"#.to_string()),
+                (
+                    "shipping".to_string(),
+                    r#"{"days":2,"method":"express"}"#.to_string()
+                ),
+                ("amount".to_string(), "1".to_string()),
+                ("ce_zone".to_string(), "us".to_string()),
+            ]
+        );
+        assert_eq!(
+            data.products,
+            vec![vec![("sku".to_string(), "A1".to_string())]]
+        );
+
+        // the document order decides which value wins a collision
+        let settings = |policy: &str| {
+            vec![
+                ("project_name".to_string(), "example.com".to_string()),
+                ("property_collision_policy".to_string(), policy.to_string()),
+            ]
+        };
+        let params = query_params(
+            &run(track, event.clone(), settings("first_wins"))
+                .unwrap()
+                .url,
+        );
+        assert_eq!(params["ce_zone"], "eu");
+        let params = query_params(&run(track, event, settings("rename")).unwrap().url);
+        assert_eq!(params["ce_zone"], "eu");
+        assert_eq!(params["ce_zone_2"], "us");
+    }
+
+    #[test]
+    fn track_and_identify_urls_are_decoded_by_scope() {
+        let event = sample_page_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let settings = vec![("project_name".to_string(), "example.com".to_string())];
+        let request = run(page, event, settings.clone()).unwrap();
+
+        let decoded = decode_url(&request.url).unwrap();
+        assert_eq!(decoded.endpoint, WOOPRA_TRACK_ENDPOINT);
+        assert_eq!(decoded.is_identify(), false);
+        assert_eq!(decoded.project(), Some("example.com"));
+        assert_eq!(decoded.event_name(), Some("pv"));
+        assert_eq!(decoded.visitor.get("user_id").unwrap(), "123");
+        assert_eq!(decoded.event.get("title").unwrap(), "page title");
+        assert_eq!(decoded.session.get("session_id").unwrap(), "random");
+        assert_eq!(decoded.params.get("language").unwrap(), "fr");
+        assert_eq!(decoded.params.contains_key("cv_user_id"), false);
+
+        let event = sample_user_event(
+            Some(Consent::Granted),
+            "abc".to_string(),
+            "fr".to_string(),
+            true,
+        );
+        let request = run(user, event, settings).unwrap();
+        let (_, path) = request.url.split_once(WOOPRA_HOST).unwrap();
+        let decoded = decode_url(path).unwrap();
+        assert_eq!(decoded.is_identify(), true);
+        assert_eq!(decoded.event_name(), None);
+        assert_eq!(decoded.visitor.get("id").unwrap(), "123");
+        assert_eq!(decoded.event.is_empty(), true);
+    }
+
+    #[test]
+    fn decoding_rejects_other_urls() {
+        assert_eq!(
+            decode_url("https://example.com/collect?a=b")
+                .unwrap_err()
+                .to_string(),
+            "Not a Woopra track, update or identify URL: https://example.com/collect"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::data_collection::TrackData;
+use crate::url_canonicalization::url_host;
+
+// event names and property of the links reported by the Woopra JS tracker
+const OUTGOING_EVENT: &str = "outgoing";
+const DOWNLOAD_EVENT: &str = "download";
+const URL_PROPERTY: &str = "url";
+
+const DEFAULT_URL_PROPERTY: &str = "link_url";
+
+// same defaults as the Woopra JS tracker
+const DEFAULT_DOWNLOAD_EXTENSIONS: &[&str] = &[
+    "avi", "css", "dmg", "doc", "docx", "eps", "exe", "js", "m4v", "mov", "mp3", "mp4", "msi",
+    "pdf", "ppt", "pptx", "rar", "svg", "txt", "vsd", "vxd", "wma", "wmv", "xls", "xlsx", "zip",
+];
+
+// derivation of Woopra's "outgoing" and "download" events from track events carrying a link URL,
+// as the JS tracker would report them
+#[derive(Debug, Clone)]
+pub struct LinkTracking {
+    pub enabled: bool,
+    // name of the track property holding the link URL
+    pub url_property: String,
+    // file extensions of downloads (lowercase, without dot)
+    pub download_extensions: Vec<String>,
+}
+
+impl Default for LinkTracking {
+    fn default() -> Self {
+        LinkTracking {
+            enabled: false,
+            url_property: DEFAULT_URL_PROPERTY.to_string(),
+            download_extensions: DEFAULT_DOWNLOAD_EXTENSIONS
+                .iter()
+                .map(|extension| extension.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl LinkTracking {
+    // downloads are recognized by their extension first, then links to another host than the
+    // page are outgoing; the link URL is sent as "ce_url", and the other properties are kept
+    pub(crate) fn apply(&self, data: &TrackData, page_url: &str) -> TrackData {
+        let link = data
+            .properties
+            .iter()
+            .find(|(key, value)| *key == self.url_property && !value.trim().is_empty())
+            .map(|(_, value)| value.trim());
+        let Some(link) = link.filter(|_| self.enabled) else {
+            return data.clone();
+        };
+
+        let name = if self.is_download(link) {
+            DOWNLOAD_EVENT
+        } else if is_outgoing(link, page_url) {
+            OUTGOING_EVENT
+        } else {
+            return data.clone();
+        };
+
+        // the link is added last, an existing "url" property collides with it like any other
+        let mut properties: Vec<(String, String)> = data
+            .properties
+            .iter()
+            .filter(|(key, _)| *key != self.url_property)
+            .cloned()
+            .collect();
+        properties.push((URL_PROPERTY.to_string(), link.to_string()));
+        TrackData {
+            name: name.to_string(),
+            properties,
+            products: data.products.clone(),
+        }
+    }
+
+    fn is_download(&self, link: &str) -> bool {
+        let path = link.split(['?', '#']).next().unwrap_or_default();
+        let path = match path.split_once("://") {
+            Some((_, rest)) => rest.split_once('/').map_or("", |(_, path)| path),
+            None => path,
+        };
+        let file = path.rsplit('/').next().unwrap_or_default();
+        match file.rsplit_once('.') {
+            Some((_, extension)) => self
+                .download_extensions
+                .iter()
+                .any(|download| download.eq_ignore_ascii_case(extension)),
+            None => false,
+        }
+    }
+}
+
+// relative links stay on the page host; "www." is ignored when comparing hosts
+fn is_outgoing(link: &str, page_url: &str) -> bool {
+    let Some(link_host) = url_host(link) else {
+        return false;
+    };
+    let Some(page_host) = url_host(page_url) else {
+        return true;
+    };
+    let normalize = |host: &str| {
+        let host = host.to_ascii_lowercase();
+        host.strip_prefix("www.")
+            .map(str::to_string)
+            .unwrap_or(host)
+    };
+    normalize(link_host) != normalize(page_host)
+}
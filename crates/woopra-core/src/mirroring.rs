@@ -0,0 +1,34 @@
+use crate::data_collection::{EdgeeRequest, HttpMethod};
+
+// mirror mode: every event is also sent to secondary Woopra projects (e.g. a sandbox during a migration)
+//
+// Woopra's tracking API only takes one project per request and components can only return one
+// request per event, so mirrored events are sent to a fan-out endpoint (on your own proxy) as a
+// POST request whose body lists the Woopra URLs to call, one per line
+#[derive(Debug, Default, Clone)]
+pub struct Mirroring {
+    pub projects: Vec<String>,
+    pub fanout_endpoint: Option<String>,
+}
+
+impl Mirroring {
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.projects.is_empty()
+    }
+}
+
+pub(crate) fn build_fanout_request(urls: &[String], fanout_endpoint: &str) -> EdgeeRequest {
+    let body = urls.join("\n");
+    let headers = vec![
+        (String::from("content-type"), String::from("text/plain")),
+        (String::from("content-length"), body.len().to_string()),
+    ];
+
+    EdgeeRequest {
+        method: HttpMethod::Post,
+        url: fanout_endpoint.to_string(),
+        headers,
+        forward_client_headers: true,
+        body,
+    }
+}
@@ -0,0 +1,284 @@
+use crate::data_collection::TrackData;
+
+const DEFAULT_CURRENCY_PROPERTY: &str = "currency";
+const ORIGINAL_CURRENCY_PROPERTY: &str = "original_currency";
+
+// active ISO 4217 currency codes
+const ISO_CURRENCIES: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD", "CAD",
+    "CDF", "CHF", "CLP", "CNY", "COP", "CRC", "CUP", "CVE", "CZK", "DJF", "DKK", "DOP", "DZD",
+    "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP", "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ",
+    "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS", "INR", "IQD", "IRR", "ISK", "JMD", "JOD",
+    "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW", "KWD", "KYD", "KZT", "LAK", "LBP", "LKR",
+    "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD", "MMK", "MNT", "MOP", "MRU", "MUR", "MVR",
+    "MWK", "MXN", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN",
+    "PGK", "PHP", "PKR", "PLN", "PYG", "QAR", "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR",
+    "SDG", "SEK", "SGD", "SHP", "SLE", "SOS", "SRD", "SSP", "STN", "SYP", "SZL", "THB", "TJS",
+    "TMT", "TND", "TOP", "TRY", "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "UYU", "UZS", "VES",
+    "VND", "VUV", "WST", "XAF", "XCD", "XOF", "XPF", "YER", "ZAR", "ZMW", "ZWL",
+];
+
+// currency symbols found in amounts (longest first, "R$" must win over "$"), ambiguous symbols
+// have no currency ("$" is also used by CAD, AUD, MXN, ..., "¥" by JPY and CNY)
+const CURRENCY_SYMBOLS: &[(&str, Option<&str>)] = &[
+    ("R$", Some("BRL")),
+    ("C$", Some("CAD")),
+    ("A$", Some("AUD")),
+    ("zł", Some("PLN")),
+    ("$", None),
+    ("€", Some("EUR")),
+    ("£", Some("GBP")),
+    ("¥", None),
+    ("₹", Some("INR")),
+    ("₩", Some("KRW")),
+    ("₽", Some("RUB")),
+    ("₺", Some("TRY")),
+    ("₪", Some("ILS")),
+    ("₫", Some("VND")),
+    ("฿", Some("THB")),
+    ("₱", Some("PHP")),
+];
+
+// languages writing decimals with a comma ("12,50"), and the regional exceptions
+const COMMA_DECIMAL_LANGUAGES: &[&str] = &[
+    "af", "az", "be", "bg", "bs", "ca", "cs", "da", "de", "el", "es", "et", "eu", "fi", "fo", "fr",
+    "gl", "hr", "hu", "hy", "id", "is", "it", "ka", "kk", "ky", "lt", "lv", "mk", "mn", "nb", "nl",
+    "nn", "no", "pl", "pt", "ro", "ru", "sk", "sl", "sq", "sr", "sv", "tr", "uk", "uz", "vi",
+];
+const DOT_DECIMAL_REGIONS: &[&str] = &["de-ch", "de-li", "it-ch", "es-mx", "es-us"];
+
+// normalization of monetary properties: amounts are sent as plain decimal numbers, the currency
+// as an ISO 4217 code, and amounts can be converted to a base currency
+#[derive(Debug, Clone)]
+pub struct MonetaryNormalization {
+    // track properties holding amounts (e.g. "revenue", "price")
+    pub properties: Vec<String>,
+    // locale of the amounts (e.g. "de-DE"), the locale of the client is used if missing
+    pub locale: Option<String>,
+    // track property holding the currency of the amounts
+    pub currency_property: String,
+    // currency of the amounts when the event does not tell it (no currency property, and no
+    // currency code or unambiguous symbol in the amounts)
+    pub default_currency: Option<String>,
+    // if set, amounts in other currencies are converted with the rates
+    pub base_currency: Option<String>,
+    // (currency, value of one unit in the base currency) pairs
+    pub rates: Vec<(String, f64)>,
+}
+
+impl Default for MonetaryNormalization {
+    fn default() -> Self {
+        MonetaryNormalization {
+            properties: vec![],
+            locale: None,
+            currency_property: DEFAULT_CURRENCY_PROPERTY.to_string(),
+            default_currency: None,
+            base_currency: None,
+            rates: vec![],
+        }
+    }
+}
+
+impl MonetaryNormalization {
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.properties.is_empty()
+    }
+
+    pub(crate) fn is_monetary(&self, key: &str) -> bool {
+        self.properties.iter().any(|property| property == key)
+    }
+
+    // the locale amounts are written in: the setting, or the locale of the client
+    pub(crate) fn locale<'a>(&'a self, client_locale: &'a str) -> &'a str {
+        self.locale.as_deref().unwrap_or(client_locale)
+    }
+
+    // amounts that cannot be parsed are sent as is, and an invalid currency property is sent
+    // as "original_currency" instead; converted amounts are rounded to 2 decimals, and the
+    // original currency is sent as "original_currency"
+    pub(crate) fn apply(&self, data: &TrackData, client_locale: &str) -> TrackData {
+        if !self.is_enabled() {
+            return data.clone();
+        }
+        let decimal = decimal_separator(self.locale(client_locale));
+
+        let explicit_currency = data
+            .properties
+            .iter()
+            .find(|(key, _)| *key == self.currency_property)
+            .map(|(_, value)| value.trim().to_ascii_uppercase());
+        let currency = explicit_currency.clone().or_else(|| {
+            data.properties
+                .iter()
+                .filter(|(key, _)| self.is_monetary(key))
+                .find_map(|(_, value)| currency_of(value))
+                .map(str::to_string)
+                .or_else(|| self.default_currency.clone())
+        });
+        let currency = currency.filter(|currency| is_iso_currency(currency));
+
+        let rate = match (&self.base_currency, &currency) {
+            (Some(base), Some(currency)) if base != currency => self
+                .rates
+                .iter()
+                .find(|(rate_currency, _)| rate_currency == currency)
+                .map(|(_, rate)| *rate),
+            _ => None,
+        };
+
+        let mut properties = Vec::with_capacity(data.properties.len() + 2);
+        for (key, value) in data.properties.iter() {
+            if *key == self.currency_property {
+                if let Some(currency) = currency.as_ref() {
+                    let sent = match rate {
+                        Some(_) => self.base_currency.clone().unwrap_or_default(),
+                        None => currency.clone(),
+                    };
+                    properties.push((key.clone(), sent));
+                } else {
+                    properties.push((ORIGINAL_CURRENCY_PROPERTY.to_string(), value.clone()));
+                }
+                continue;
+            }
+            if !self.is_monetary(key) {
+                properties.push((key.clone(), value.clone()));
+                continue;
+            }
+            let value = match (parse_amount(value, decimal), rate) {
+                (Some(amount), Some(rate)) => {
+                    format_amount((amount * rate * 100.0).round() / 100.0)
+                }
+                (Some(amount), None) => format_amount(amount),
+                (None, _) => value.clone(),
+            };
+            properties.push((key.clone(), value));
+        }
+
+        if explicit_currency.is_none() {
+            let sent = match rate {
+                Some(_) => self.base_currency.clone(),
+                None => currency.clone(),
+            };
+            if let Some(sent) = sent {
+                properties.push((self.currency_property.clone(), sent));
+            }
+        }
+        if let (Some(currency), Some(_)) = (currency, rate) {
+            properties.push((ORIGINAL_CURRENCY_PROPERTY.to_string(), currency));
+        }
+
+        TrackData {
+            name: data.name.clone(),
+            properties,
+            products: data.products.clone(),
+        }
+    }
+}
+
+pub(crate) fn is_iso_currency(code: &str) -> bool {
+    ISO_CURRENCIES.contains(&code)
+}
+
+// the decimal separator of a locale ("fr-FR", "pt_BR", "en-US,en;q=0.9"), "." for the languages
+// not known to write decimals with a comma, and None without locale
+pub(crate) fn decimal_separator(locale: &str) -> Option<char> {
+    let locale = locale
+        .split(',')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .replace('_', "-")
+        .to_ascii_lowercase();
+    let language = locale.split('-').next().unwrap_or_default();
+    if language.is_empty() {
+        return None;
+    }
+    if DOT_DECIMAL_REGIONS.contains(&locale.as_str()) {
+        return Some('.');
+    }
+    if COMMA_DECIMAL_LANGUAGES.contains(&language) {
+        Some(',')
+    } else {
+        Some('.')
+    }
+}
+
+// the currency written in an amount, as an unambiguous symbol ("€12") or an ISO code
+// ("12.50 EUR", "$12 CAD")
+pub(crate) fn currency_of(value: &str) -> Option<&'static str> {
+    if let Some((_, Some(currency))) = CURRENCY_SYMBOLS
+        .iter()
+        .find(|(symbol, _)| value.contains(symbol))
+    {
+        return Some(currency);
+    }
+    let code: String = value.chars().filter(|c| c.is_alphabetic()).collect();
+    let code = code.to_ascii_uppercase();
+    ISO_CURRENCIES.iter().find(|iso| **iso == code).copied()
+}
+
+// parses amounts written with currency symbols or codes, thousands separators (",", ".", spaces,
+// apostrophes) and either decimal separator: when a single separator is ambiguous ("1,234"),
+// the decimal separator of the locale decides (unless the integer part is "0"), otherwise "." is
+// the decimal separator, and "," is one unless followed by exactly 3 digits
+pub(crate) fn parse_amount(value: &str, decimal: Option<char>) -> Option<f64> {
+    let mut amount = value.to_string();
+    for (symbol, _) in CURRENCY_SYMBOLS.iter() {
+        amount = amount.replace(symbol, "");
+    }
+    let mut amount: String = amount
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '\'' | '’'))
+        .collect();
+    let code: String = amount.chars().filter(|c| c.is_alphabetic()).collect();
+    if !code.is_empty() {
+        if !is_iso_currency(&code.to_ascii_uppercase()) {
+            return None;
+        }
+        amount.retain(|c| !c.is_alphabetic());
+    }
+
+    let dots = amount.matches('.').count();
+    let commas = amount.matches(',').count();
+    let decimal_separator = match (dots, commas) {
+        (0, 0) => None,
+        (_, 0) | (0, _) => {
+            let separator = if dots > 0 { '.' } else { ',' };
+            let count = dots.max(commas);
+            let (integer, decimals) = amount.rsplit_once(separator).unwrap_or_default();
+            // "0.125" has no thousands, whatever the locale
+            let integer = integer.trim_start_matches(['-', '+']);
+            let grouped = decimals.len() == 3 && !(integer.starts_with('0') && count == 1);
+            match decimal {
+                _ if count > 1 => None,
+                Some(decimal) if decimal == separator => Some(separator),
+                Some(_) if grouped => None,
+                Some(_) => Some(separator),
+                None if separator == ',' && grouped => None,
+                None => Some(separator),
+            }
+        }
+        _ => amount
+            .rfind(['.', ','])
+            .and_then(|index| amount[index..].chars().next()),
+    };
+
+    let amount: String = amount
+        .chars()
+        .filter_map(|c| match c {
+            '.' | ',' if Some(c) == decimal_separator => Some('.'),
+            '.' | ',' => None,
+            c => Some(c),
+        })
+        .collect();
+    amount
+        .parse::<f64>()
+        .ok()
+        .filter(|amount| amount.is_finite())
+}
+
+// plain decimal representation, without exponent or trailing zeros
+pub(crate) fn format_amount(amount: f64) -> String {
+    amount.to_string()
+}
@@ -0,0 +1,227 @@
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+static EMAIL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap());
+static CREDIT_CARD_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:\d[ -]?){12,18}\d\b").unwrap());
+static PHONE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\d{1,4}(?:[ .-]\d{1,4}){1,6}").unwrap()
+});
+
+const REDACTED: &str = "[REDACTED]";
+
+// characters that are percent-encoded again in the URL components where PII was found
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+// what to do with a field containing PII
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PiiAction {
+    #[default]
+    None,
+    Redact, // replace each match with "[REDACTED]"
+    Hash,   // replace each match with its SHA-256 hex digest
+    Drop,   // remove the whole field from the payload
+}
+
+impl FromStr for PiiAction {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => Ok(PiiAction::None),
+            "redact" => Ok(PiiAction::Redact),
+            "hash" => Ok(PiiAction::Hash),
+            "drop" => Ok(PiiAction::Drop),
+            other => anyhow::bail!("Invalid PII action: {other}"),
+        }
+    }
+}
+
+// PII detection applied to every outgoing string field (except the required ones)
+#[derive(Debug, Clone)]
+pub struct PiiScrubbing {
+    pub action: PiiAction,
+    pub detect_emails: bool,
+    pub detect_phones: bool,
+    pub detect_credit_cards: bool,
+    // Woopra field names that are never scrubbed (e.g. "cv_email")
+    pub exempt_fields: Vec<String>,
+}
+
+impl Default for PiiScrubbing {
+    fn default() -> Self {
+        PiiScrubbing {
+            action: PiiAction::None,
+            detect_emails: true,
+            detect_phones: true,
+            detect_credit_cards: true,
+            exempt_fields: vec![],
+        }
+    }
+}
+
+impl PiiScrubbing {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.action != PiiAction::None
+    }
+
+    // returns the scrubbed value, or None if the field has to be dropped
+    pub(crate) fn scrub(&self, field: &str, value: &str) -> Option<String> {
+        if !self.is_enabled() || self.exempt_fields.iter().any(|exempt| exempt == field) {
+            return Some(value.to_string());
+        }
+
+        let mut detected = false;
+        let scrubbed = if is_percent_encoded(value) {
+            self.scrub_url(value, &mut detected)
+        } else {
+            self.scrub_text(value, &mut detected)
+        };
+
+        if detected && self.action == PiiAction::Drop {
+            None
+        } else {
+            Some(scrubbed)
+        }
+    }
+
+    fn scrub_text(&self, value: &str, detected: &mut bool) -> String {
+        let mut scrubbed = value.to_string();
+        if self.detect_emails {
+            scrubbed = self.replace(&EMAIL_REGEX, &scrubbed, detected, |_| true);
+        }
+        if self.detect_credit_cards {
+            scrubbed = self.replace(&CREDIT_CARD_REGEX, &scrubbed, detected, is_luhn_valid);
+        }
+        if self.detect_phones {
+            scrubbed = self.replace(&PHONE_REGEX, &scrubbed, detected, is_phone_number);
+        }
+        scrubbed
+    }
+
+    // URLs carry PII percent-encoded (e.g. "?email=jane%40example.com"): each component between
+    // delimiters is decoded before detection, and re-encoded only if PII was found in it
+    fn scrub_url(&self, value: &str, detected: &mut bool) -> String {
+        value
+            .split_inclusive(URL_DELIMITERS)
+            .map(|part| {
+                let (component, delimiter) = match part.strip_suffix(URL_DELIMITERS) {
+                    Some(component) => (component, &part[component.len()..]),
+                    None => (part, ""),
+                };
+                let decoded = percent_decode_str(component).decode_utf8_lossy();
+                let mut found = false;
+                let scrubbed = self.scrub_text(&decoded, &mut found);
+                if !found {
+                    return part.to_string();
+                }
+                *detected = true;
+                format!(
+                    "{}{delimiter}",
+                    utf8_percent_encode(&scrubbed, URL_COMPONENT)
+                )
+            })
+            .collect()
+    }
+
+    fn replace(
+        &self,
+        regex: &Regex,
+        value: &str,
+        detected: &mut bool,
+        is_match: fn(&str) -> bool,
+    ) -> String {
+        regex
+            .replace_all(value, |captures: &regex::Captures| {
+                let matched = &captures[0];
+                if !is_match(matched) {
+                    return matched.to_string();
+                }
+                *detected = true;
+                match self.action {
+                    PiiAction::Hash => sha256_hex(matched),
+                    _ => REDACTED.to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+const URL_DELIMITERS: [char; 6] = ['/', '?', '#', '&', '=', ';'];
+
+// whether the value holds at least one percent-encoded byte (e.g. "%40")
+fn is_percent_encoded(value: &str) -> bool {
+    value.as_bytes().windows(3).any(|window| {
+        window[0] == b'%' && window[1].is_ascii_hexdigit() && window[2].is_ascii_hexdigit()
+    })
+}
+
+pub(crate) fn sha256_hex(value: &str) -> String {
+    to_hex(&Sha256::digest(value.as_bytes()))
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn is_luhn_valid(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| {
+            if index % 2 == 1 {
+                let doubled = digit * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                *digit
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+// phone numbers need 9 to 15 digits and a single kind of separator between digit groups,
+// so that dates, times, IP addresses and version numbers aren't mistaken for phone numbers
+fn is_phone_number(candidate: &str) -> bool {
+    let digit_count = candidate.chars().filter(|c| c.is_ascii_digit()).count();
+    if !(9..=15).contains(&digit_count) {
+        return false;
+    }
+
+    // skip the country code and the area code, they can use their own separator
+    let mut local_part = candidate;
+    if let Some(rest) = local_part.strip_prefix('+') {
+        local_part = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    }
+    if let Some((_, rest)) = local_part.rsplit_once(')') {
+        local_part = rest;
+    }
+    let local_part = local_part.trim_start_matches([' ', '.', '-']);
+
+    let mut separators = local_part.chars().filter(|c| !c.is_ascii_digit());
+    let Some(separator) = separators.next() else {
+        return false;
+    };
+    if !separators.all(|other| other == separator) {
+        return false;
+    }
+    let is_ipv4 = separator == '.' && local_part.split('.').count() == 4;
+    !is_ipv4
+}
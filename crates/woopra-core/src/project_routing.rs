@@ -0,0 +1,46 @@
+use crate::data_collection::Dict;
+use crate::url_canonicalization::{host_matches, url_host};
+
+// routing of events to several Woopra projects, based on the page hostname or a property value
+#[derive(Debug, Default, Clone)]
+pub struct ProjectRouting {
+    // (hostname or property value, project) pairs, "*.example.com" matches subdomains
+    pub routes: Vec<(String, String)>,
+    // if set, the value of this property is matched against the routes instead of the hostname
+    pub property: Option<String>,
+}
+
+impl ProjectRouting {
+    // returns the project of the first matching route, or the default project
+    pub(crate) fn resolve(
+        &self,
+        default_project: &str,
+        page_url: &str,
+        properties: &[&Dict],
+    ) -> String {
+        if let Some(property) = self.property.as_ref() {
+            let value = properties
+                .iter()
+                .flat_map(|dict| dict.iter())
+                .find(|(key, _)| key == property)
+                .map(|(_, value)| value);
+            if let Some(value) = value {
+                if let Some((_, project)) = self.routes.iter().find(|(route, _)| route == value) {
+                    return project.clone();
+                }
+            }
+        }
+
+        if let Some(host) = url_host(page_url) {
+            if let Some((_, project)) = self
+                .routes
+                .iter()
+                .find(|(route, _)| host_matches(route, host))
+            {
+                return project.clone();
+            }
+        }
+
+        default_project.to_string()
+    }
+}
@@ -0,0 +1,254 @@
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+// what to do when values compete for the same Woopra key: "foo" and its explicitly prefixed
+// form (e.g. "cv_foo"), or the same key set twice with different values
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    #[default]
+    ExplicitPrefixWins, // keep the value of the prefixed key (or the value set last)
+    FirstWins, // keep the value set first
+    Rename,    // keep both, the value set last is sent as "foo_2"
+}
+
+impl FromStr for CollisionPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "explicit_prefix_wins" => Ok(CollisionPolicy::ExplicitPrefixWins),
+            "first_wins" => Ok(CollisionPolicy::FirstWins),
+            "rename" => Ok(CollisionPolicy::Rename),
+            other => anyhow::bail!("Invalid property collision policy: {other}"),
+        }
+    }
+}
+
+// the Woopra scope a page or track property is sent in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyScope {
+    Visitor, // "cv_"
+    Event,   // "ce_"
+    Session, // "cs_"
+}
+
+impl FromStr for PropertyScope {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "visitor" => Ok(PropertyScope::Visitor),
+            "event" => Ok(PropertyScope::Event),
+            "session" => Ok(PropertyScope::Session),
+            other => anyhow::bail!("Invalid property scope: {other}"),
+        }
+    }
+}
+
+// routing of page and track properties to the visitor or session scope, either by a key
+// namespace ("visitor.plan") or by name from settings ("plan=visitor")
+#[derive(Debug, Default, Clone)]
+pub struct ScopeRouting {
+    // (property name, scope) pairs
+    pub routes: Vec<(String, PropertyScope)>,
+}
+
+impl ScopeRouting {
+    // returns the scope of a property and its name without namespace,
+    // or None if the property stays in its default scope
+    pub(crate) fn route<'a>(&self, key: &'a str) -> Option<(PropertyScope, &'a str)> {
+        let namespaces = [
+            ("visitor.", PropertyScope::Visitor),
+            ("event.", PropertyScope::Event),
+            ("session.", PropertyScope::Session),
+        ];
+        for (namespace, scope) in namespaces {
+            match key.strip_prefix(namespace) {
+                Some(name) if !name.is_empty() => return Some((scope, name)),
+                _ => {}
+            }
+        }
+        self.routes
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, scope)| (*scope, key))
+    }
+}
+
+// the Woopra scopes of custom properties, each one with its own key prefix
+pub(crate) trait Scope {
+    const PREFIX: &'static str;
+}
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct VisitorScope;
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct EventScope;
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SessionScope;
+
+impl Scope for VisitorScope {
+    const PREFIX: &'static str = "cv_";
+}
+
+impl Scope for EventScope {
+    const PREFIX: &'static str = "ce_";
+}
+
+impl Scope for SessionScope {
+    const PREFIX: &'static str = "cs_";
+}
+
+// custom properties of one scope, in insertion order; keys are stored as supplied
+// (with or without prefix) and only prefixed when serialized
+#[derive(Debug, Clone)]
+pub(crate) struct Properties<S: Scope>(Vec<(String, String)>, PhantomData<S>);
+
+impl<S: Scope> Default for Properties<S> {
+    fn default() -> Self {
+        Properties(Vec::new(), PhantomData)
+    }
+}
+
+impl<S: Scope> Properties<S> {
+    // the Woopra key of a property, e.g. "cs_channel" for "channel" or "cs_channel"
+    pub(crate) fn prefixed(key: &str) -> String {
+        format!("{}{}", S::PREFIX, unprefixed(key, S::PREFIX))
+    }
+
+    // replaces the value of an existing key in place (properties set by the mapping)
+    pub(crate) fn insert(&mut self, key: String, value: String) {
+        match self.0.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => *existing = value,
+            None => self.0.push((key, value)),
+        }
+    }
+
+    // adds a value even if the key is already set (properties supplied by the event), so that
+    // competing values are resolved as collisions; a value already set under the key is skipped
+    pub(crate) fn push(&mut self, key: String, value: String) {
+        let property = (key, value);
+        if !self.0.contains(&property) {
+            self.0.push(property);
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self, key: &str) -> Option<&mut String> {
+        self.0
+            .iter_mut()
+            .find(|(existing, _)| existing == key)
+            .map(|(_, value)| value)
+    }
+
+    // whether the key is set, with or without prefix
+    pub(crate) fn contains(&self, key: &str) -> bool {
+        let key = unprefixed(key, S::PREFIX);
+        self.0
+            .iter()
+            .any(|(existing, _)| unprefixed(existing, S::PREFIX) == key)
+    }
+
+    // removes the key, with or without prefix
+    pub(crate) fn remove(&mut self, key: &str) {
+        let key = unprefixed(key, S::PREFIX);
+        self.0
+            .retain(|(existing, _)| unprefixed(existing, S::PREFIX) != key);
+    }
+
+    // removes the key, with or without prefix, and returns the value set last
+    pub(crate) fn take(&mut self, key: &str) -> Option<String> {
+        let key = unprefixed(key, S::PREFIX);
+        let value = self
+            .0
+            .iter()
+            .rev()
+            .find(|(existing, _)| unprefixed(existing, S::PREFIX) == key)
+            .map(|(_, value)| value.clone());
+        self.remove(key);
+        value
+    }
+
+    // the first "key_2", "key_3", ... that is not set, with or without prefix
+    pub(crate) fn renamed(&self, key: &str) -> String {
+        (2..)
+            .map(|suffix| format!("{key}_{suffix}"))
+            .find(|renamed| !self.contains(renamed))
+            .unwrap()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter().map(|(key, value)| (key, value))
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut String)> {
+        self.0.iter_mut().map(|(key, value)| (&*key, value))
+    }
+
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(&String, &mut String) -> bool) {
+        self.0.retain_mut(|(key, value)| keep(key, value));
+    }
+
+    // applies the policy to the values sent under the same Woopra key,
+    // and returns the prefixed Woopra keys that collided
+    pub(crate) fn resolve_collisions(&mut self, policy: CollisionPolicy) -> Vec<String> {
+        let prefix = S::PREFIX;
+        let mut collisions = Vec::new();
+        let mut index = 0;
+        while index < self.0.len() {
+            let key = unprefixed(&self.0[index].0, prefix).to_string();
+            let Some(other) = (index + 1..self.0.len())
+                .find(|other| unprefixed(&self.0[*other].0, prefix) == key)
+            else {
+                index += 1;
+                continue;
+            };
+
+            let prefixed = format!("{prefix}{key}");
+            if !collisions.contains(&prefixed) {
+                collisions.push(prefixed);
+            }
+            match policy {
+                CollisionPolicy::ExplicitPrefixWins
+                    if self.0[other].0.starts_with(prefix)
+                        || !self.0[index].0.starts_with(prefix) =>
+                {
+                    self.0.remove(index);
+                    continue;
+                }
+                CollisionPolicy::ExplicitPrefixWins | CollisionPolicy::FirstWins => {
+                    self.0.remove(other);
+                }
+                CollisionPolicy::Rename => {
+                    self.0[other].0 = self.renamed(&key);
+                }
+            }
+        }
+        collisions
+    }
+}
+
+// serialized as a flattened map of prefixed keys; a key set both with and without prefix
+// is sent once (the value set last), unless the collisions were resolved beforehand
+impl<S: Scope> Serialize for Properties<S> {
+    fn serialize<T>(&self, serializer: T) -> Result<T::Ok, T::Error>
+    where
+        T: serde::Serializer,
+    {
+        let mut prefixed = Properties::<S>::default();
+        for (key, value) in self.iter() {
+            prefixed.insert(Self::prefixed(key), value.clone());
+        }
+        serializer.collect_map(prefixed.iter())
+    }
+}
+
+fn unprefixed<'a>(key: &'a str, prefix: &str) -> &'a str {
+    key.strip_prefix(prefix).unwrap_or(key)
+}
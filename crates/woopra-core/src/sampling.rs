@@ -0,0 +1,45 @@
+use sha2::{Digest, Sha256};
+
+use crate::data_collection::UserData;
+
+// deterministic sampling: visitors are bucketed by hashing their device identifier,
+// so a sampled visitor's whole journey is either kept or dropped, before and after logging in
+#[derive(Debug, Clone)]
+pub struct Sampling {
+    pub rate: f64, // between 0 and 1
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling { rate: 1.0 }
+    }
+}
+
+impl Sampling {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.rate < 1.0
+    }
+
+    // the user of the event context is used for every event type, the user id only when the
+    // device ids are missing; events without any visitor identifier are always kept
+    pub(crate) fn is_kept(&self, user: &UserData) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        let Some(visitor_id) = [&user.edgee_id, &user.anonymous_id, &user.user_id]
+            .into_iter()
+            .find(|id| !id.is_empty())
+        else {
+            return true;
+        };
+        bucket(visitor_id) < self.rate
+    }
+}
+
+// map the visitor identifier to a number in [0, 1)
+fn bucket(visitor_id: &str) -> f64 {
+    let digest = Sha256::digest(visitor_id.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    (u64::from_be_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+}
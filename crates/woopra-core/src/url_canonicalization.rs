@@ -0,0 +1,136 @@
+// URL canonicalization rules applied to `ce_uri` and `referer` before they are sent to Woopra
+// (query strings often carry session tokens, emails or tracking parameters)
+#[derive(Debug, Default, Clone)]
+pub struct UrlCanonicalization {
+    // query params to remove, a trailing "*" matches any param starting with the prefix (e.g. "utm_*")
+    pub strip_query_params: Vec<String>,
+    // if not empty, every query param that isn't listed here is removed
+    pub allowed_query_params: Vec<String>,
+    pub strip_fragment: bool,
+    pub lowercase_host: bool,
+}
+
+impl UrlCanonicalization {
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.strip_query_params.is_empty()
+            || !self.allowed_query_params.is_empty()
+            || self.strip_fragment
+            || self.lowercase_host
+    }
+
+    pub(crate) fn apply(&self, url: &str) -> String {
+        if !self.is_enabled() {
+            return url.to_string();
+        }
+
+        let (without_fragment, fragment) = match url.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (url, None),
+        };
+        let (base, query) = match without_fragment.split_once('?') {
+            Some((base, query)) => (base, Some(query)),
+            None => (without_fragment, None),
+        };
+
+        let mut canonical = if self.lowercase_host {
+            lowercase_host(base)
+        } else {
+            base.to_string()
+        };
+
+        if let Some(query) = query {
+            let kept: Vec<&str> = query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .filter(|pair| {
+                    let name = pair.split_once('=').map_or(*pair, |(name, _)| name);
+                    self.keep_query_param(name)
+                })
+                .collect();
+            if !kept.is_empty() {
+                canonical.push('?');
+                canonical.push_str(&kept.join("&"));
+            }
+        }
+
+        if let Some(fragment) = fragment {
+            if !self.strip_fragment {
+                canonical.push('#');
+                canonical.push_str(fragment);
+            }
+        }
+
+        canonical
+    }
+
+    fn keep_query_param(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        if !self.allowed_query_params.is_empty() {
+            return self
+                .allowed_query_params
+                .iter()
+                .any(|pattern| matches_pattern(pattern, &name));
+        }
+        !self
+            .strip_query_params
+            .iter()
+            .any(|pattern| matches_pattern(pattern, &name))
+    }
+}
+
+// patterns are lowercased when settings are parsed
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+// extract the host of an absolute URL (without userinfo and port)
+pub(crate) fn url_host(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+// match a host against a pattern, "*.example.com" matches example.com and all its subdomains
+pub(crate) fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let host = host.to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host == domain || host.ends_with(&format!(".{domain}")),
+        None => host == pattern,
+    }
+}
+
+// lowercase the host part of the authority, leaving userinfo and path untouched
+fn lowercase_host(base: &str) -> String {
+    let Some(scheme_end) = base.find("://") else {
+        return base.to_string();
+    };
+    let authority_start = scheme_end + 3;
+    let authority_end = base[authority_start..]
+        .find('/')
+        .map_or(base.len(), |index| authority_start + index);
+    let authority = &base[authority_start..authority_end];
+    let host_start = authority.rfind('@').map_or(0, |index| index + 1);
+
+    format!(
+        "{}{}{}{}",
+        base[..authority_start].to_ascii_lowercase(),
+        &authority[..host_start],
+        authority[host_start..].to_ascii_lowercase(),
+        &base[authority_end..],
+    )
+}
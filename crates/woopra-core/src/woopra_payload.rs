@@ -0,0 +1,633 @@
+use serde::Serialize;
+
+use crate::data_collection::{Event, TrackData};
+use crate::identifier_hashing::IdentifierHashing;
+use crate::pii::PiiScrubbing;
+use crate::properties::{
+    CollisionPolicy, EventScope, Properties, PropertyScope, Scope, ScopeRouting, SessionScope,
+    VisitorScope,
+};
+use crate::url_canonicalization::UrlCanonicalization;
+use crate::{DefaultProperties, DEFAULT_APP_NAME};
+
+// reserved page/track property used to override the idle timeout of a single event
+const TIMEOUT_PROPERTY: &str = "woopra_timeout";
+
+// reserved track property holding the id of a previous event to update
+const UPDATE_PROPERTY: &str = "woopra_update_id";
+
+// event property exposing the Edgee event uuid sent as event id
+const EVENT_ID_PROPERTY: &str = "edgee_event_id";
+
+// warning property listing the Woopra keys that several values competed for
+const COLLISIONS_PROPERTY: &str = "property_collisions";
+
+// common behaviour of the Woopra payloads
+pub(crate) trait WoopraPayload: Serialize {
+    fn set_project(&mut self, project: &str);
+}
+
+// documentation: https://docs.woopra.com/reference/track-ce
+// this struct is only used with Page and Track events
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct WoopraPayloadTrack {
+    // only these 3 fields are required
+    project: String,
+    event: String,
+    timestamp: String,
+
+    // id of the event (the Edgee event uuid)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+
+    // all properties are prefixed with "cv_" (visitor), "ce_" (event), "cs_" (session)
+    // when serialized as flattened maps
+    #[serde(skip_serializing_if = "Properties::is_empty", flatten)]
+    visitor_properties: Properties<VisitorScope>,
+    #[serde(skip_serializing_if = "Properties::is_empty", flatten)]
+    event_properties: Properties<EventScope>,
+    #[serde(skip_serializing_if = "Properties::is_empty", flatten)]
+    session_properties: Properties<SessionScope>,
+
+    // all the other fields are optional
+    #[serde(skip_serializing_if = "Option::is_none")]
+    screen: Option<String>, // e.g. "1920x1080"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>, // e.g. "en-US"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    referer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<String>, // idle timeout in milliseconds (Woopra default is 30000)
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    browser: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    os: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    app: Option<String>,
+}
+
+impl WoopraPayloadTrack {
+    // the builtin properties are set here, before any custom property, so that custom
+    // properties sent under the same key are reported as collisions
+    pub(crate) fn new(edgee_event: &Event, project: String, event: String) -> anyhow::Result<Self> {
+        let mut payload = WoopraPayloadTrack {
+            event,
+            project,
+            app: Some(DEFAULT_APP_NAME.to_string()), // custom app value (like a special SDK)
+            timestamp: edgee_event.timestamp.to_string(),
+            ..WoopraPayloadTrack::default()
+        };
+
+        // title, url and referrer from context.page
+        payload.set_page_fields(&edgee_event.context.page);
+
+        // language/locale
+        if !edgee_event.context.client.locale.is_empty() {
+            payload.language = Some(edgee_event.context.client.locale.clone());
+        }
+
+        // user agent
+        if !edgee_event
+            .context
+            .client
+            .user_agent_full_version_list
+            .is_empty()
+        {
+            payload.browser = Some(
+                edgee_event
+                    .context
+                    .client
+                    .user_agent_full_version_list
+                    .clone(),
+            );
+        }
+
+        // OS name and version
+        if !edgee_event.context.client.os_name.is_empty() {
+            if !edgee_event.context.client.os_version.is_empty() {
+                payload.os = Some(format!(
+                    "{} {}",
+                    edgee_event.context.client.os_name.clone(),
+                    edgee_event.context.client.os_version.clone(),
+                ));
+            } else {
+                payload.os = Some(edgee_event.context.client.os_name.clone());
+            }
+        }
+
+        // screen size
+        if edgee_event.context.client.screen_width.is_positive()
+            && edgee_event.context.client.screen_height.is_positive()
+        {
+            payload.screen = Some(format!(
+                "{:?}x{:?}",
+                edgee_event.context.client.screen_width.clone(),
+                edgee_event.context.client.screen_height.clone()
+            ));
+        }
+
+        // user ids
+        if !edgee_event.context.user.anonymous_id.is_empty() {
+            payload.visitor_properties.insert(
+                "anonymous_id".to_string(),
+                edgee_event.context.user.anonymous_id.clone(),
+            );
+        }
+        if !edgee_event.context.user.user_id.is_empty() {
+            payload.visitor_properties.insert(
+                "user_id".to_string(),
+                edgee_event.context.user.user_id.clone(),
+            );
+        }
+
+        // country code & IP address
+        if !edgee_event.context.client.country_code.is_empty() {
+            payload.visitor_properties.insert(
+                "country".to_string(),
+                edgee_event.context.client.country_code.clone(),
+            );
+        }
+        if !edgee_event.context.client.ip.is_empty() {
+            payload.ip = Some(edgee_event.context.client.ip.clone());
+        }
+
+        // session id and count
+        payload.session_properties.insert(
+            "session_id".to_string(),
+            edgee_event.context.session.session_id.clone(),
+        );
+        payload.session_properties.insert(
+            "session_count".to_string(),
+            edgee_event
+                .context
+                .session
+                .session_count
+                .clone()
+                .to_string(),
+        );
+
+        Ok(payload)
+    }
+
+    // this method can be used to set the page title, url and referrer (from event.data or
+    // context.page), the ones of event.data replace the ones of context.page
+    pub(crate) fn set_page_fields(&mut self, page: &crate::data_collection::PageData) {
+        if !page.title.is_empty() {
+            self.event_properties
+                .insert("title".to_string(), page.title.clone());
+        }
+        if !page.url.is_empty() {
+            self.event_properties
+                .insert("uri".to_string(), page.url.clone());
+        }
+        if !page.referrer.is_empty() {
+            self.referer = Some(page.referrer.clone());
+        }
+    }
+
+    // this method adds the custom properties of context.page and context.user
+    pub(crate) fn add_context_properties(&mut self, edgee_event: &Event, routing: &ScopeRouting) {
+        self.add_page_properties(&edgee_event.context.page, routing);
+        for (key, value) in edgee_event.context.user.properties.iter() {
+            self.visitor_properties.push(key.to_string(), value.clone());
+        }
+    }
+
+    // this method can be used to add page properties to the payload (from event.data or context.page)
+    pub(crate) fn add_page_properties(
+        &mut self,
+        page: &crate::data_collection::PageData,
+        routing: &ScopeRouting,
+    ) {
+        for (key, value) in page.properties.iter() {
+            if key == TIMEOUT_PROPERTY {
+                self.set_timeout_override(value);
+                continue;
+            }
+            if self.add_routed_property(key, value, routing) {
+                continue;
+            }
+            let key = key.replace(" ", "_");
+            self.event_properties
+                .push(format!("page_{key}"), value.to_string());
+        }
+    }
+
+    // this method cleans up the page URL and referrer according to the canonicalization settings
+    pub(crate) fn canonicalize_urls(&mut self, canonicalization: &UrlCanonicalization) {
+        if !canonicalization.is_enabled() {
+            return;
+        }
+        if let Some(uri) = self.event_properties.get_mut("uri") {
+            *uri = canonicalization.apply(uri);
+        }
+        if let Some(referer) = self.referer.as_mut() {
+            *referer = canonicalization.apply(referer);
+        }
+    }
+
+    // this method replaces user ids and the selected user properties with their keyed hash
+    pub(crate) fn hash_identifiers(&mut self, hashing: &IdentifierHashing) {
+        if !hashing.is_enabled() {
+            return;
+        }
+        for (key, value) in self.visitor_properties.iter_mut() {
+            if key == "user_id" || key == "anonymous_id" || hashing.is_hashed_property(key) {
+                *value = hashing.hash(value);
+            }
+        }
+    }
+
+    // this method applies PII scrubbing to every optional field and custom property
+    // (project, event and timestamp are required and left untouched, like the updated event id)
+    pub(crate) fn scrub_pii(&mut self, scrubbing: &PiiScrubbing) {
+        if !scrubbing.is_enabled() {
+            return;
+        }
+        scrub_properties(&mut self.visitor_properties, scrubbing);
+        scrub_properties(&mut self.event_properties, scrubbing);
+        scrub_properties(&mut self.session_properties, scrubbing);
+        scrub_field(&mut self.screen, "screen", scrubbing);
+        scrub_field(&mut self.language, "language", scrubbing);
+        scrub_field(&mut self.referer, "referer", scrubbing);
+        scrub_field(&mut self.timeout, "timeout", scrubbing);
+        scrub_field(&mut self.browser, "browser", scrubbing);
+        scrub_field(&mut self.os, "os", scrubbing);
+        scrub_field(&mut self.device, "device", scrubbing);
+        scrub_field(&mut self.ip, "ip", scrubbing);
+        scrub_field(&mut self.app, "app", scrubbing);
+    }
+
+    // this method can be used to add track properties to the payload (from event.data)
+    pub(crate) fn add_track_properties(
+        &mut self,
+        data: &crate::data_collection::TrackData,
+        routing: &ScopeRouting,
+    ) {
+        // track data properties
+        if !data.properties.is_empty() {
+            for (key, value) in data.properties.clone().iter() {
+                if key == TIMEOUT_PROPERTY {
+                    self.set_timeout_override(value);
+                    continue;
+                }
+                if self.add_routed_property(key, value, routing) {
+                    continue;
+                }
+                self.event_properties
+                    .push(key.to_string(), value.to_string());
+            }
+        }
+    }
+
+    // page and track properties are event properties, unless they are routed to a scope
+    // or explicitly prefixed with "cs_" (custom session properties, e.g. "cs_channel")
+    fn add_routed_property(&mut self, key: &str, value: &str, routing: &ScopeRouting) -> bool {
+        let (scope, key) = match routing.route(key) {
+            Some(route) => route,
+            None if key.starts_with(SessionScope::PREFIX) => (PropertyScope::Session, key),
+            None => return false,
+        };
+        let (key, value) = (key.to_string(), value.to_string());
+        match scope {
+            PropertyScope::Visitor => self.visitor_properties.push(key, value),
+            PropertyScope::Event => self.event_properties.push(key, value),
+            PropertyScope::Session => self.session_properties.push(key, value),
+        }
+        true
+    }
+
+    // this method sends the Edgee event uuid as event id and as the reserved "ce_edgee_event_id"
+    // property, so that retries of the same event carry the same key
+    pub(crate) fn set_event_id(&mut self, uuid: &str) {
+        let uuid = uuid.trim();
+        if uuid.is_empty() {
+            return;
+        }
+        self.id = Some(uuid.to_string());
+        self.event_properties.remove(EVENT_ID_PROPERTY);
+        self.event_properties
+            .insert(EVENT_ID_PROPERTY.to_string(), uuid.to_string());
+    }
+
+    // invalid overrides are ignored, so that the event is still sent with the default timeout
+    fn set_timeout_override(&mut self, value: &str) {
+        if let Ok(timeout) = value.trim().parse::<u64>() {
+            if timeout > 0 {
+                self.timeout = Some(timeout.to_string());
+            }
+        }
+    }
+
+    // this method sets the idle timeout from settings, unless the event overrides it
+    pub(crate) fn set_default_timeout(&mut self, timeout: Option<u64>) {
+        if self.timeout.is_none() {
+            self.timeout = timeout.map(|timeout| timeout.to_string());
+        }
+    }
+
+    // this method tags the event with the sample rate it was kept with, the mapping owns this
+    // key (an event property with the same name is replaced)
+    pub(crate) fn add_sample_rate(&mut self, rate: f64) {
+        self.event_properties.remove("sample_rate");
+        self.event_properties
+            .insert("sample_rate".to_string(), rate.to_string());
+    }
+
+    // this method overrides the default app value
+    pub(crate) fn set_app(&mut self, app: &str) {
+        if !app.is_empty() {
+            self.app = Some(app.to_string());
+        }
+    }
+
+    // this method adds the component version (and build id, if provided at compile time)
+    // as event properties, the mapping owns these keys (an event property with the same name
+    // is replaced, like the event id)
+    pub(crate) fn add_component_metadata(&mut self) {
+        self.event_properties.remove("edgee_component_version");
+        self.event_properties.insert(
+            "edgee_component_version".to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        );
+        if let Some(build) = option_env!("WOOPRA_COMPONENT_BUILD") {
+            self.event_properties.remove("edgee_component_build");
+            self.event_properties
+                .insert("edgee_component_build".to_string(), build.to_string());
+        }
+    }
+
+    // this method resolves the values competing for the same key (with or without prefix),
+    // and lists them in the "ce_property_collisions" warning property
+    pub(crate) fn resolve_collisions(&mut self, policy: CollisionPolicy) {
+        let mut collisions = self.visitor_properties.resolve_collisions(policy);
+        collisions.extend(self.event_properties.resolve_collisions(policy));
+        collisions.extend(self.session_properties.resolve_collisions(policy));
+        if !collisions.is_empty() {
+            self.event_properties
+                .insert(COLLISIONS_PROPERTY.to_string(), collisions.join(","));
+        }
+    }
+
+    // this method adds the static default properties from settings,
+    // values supplied by the event always take precedence
+    pub(crate) fn add_default_properties(&mut self, defaults: &DefaultProperties) {
+        merge_default_properties(&mut self.visitor_properties, &defaults.visitor);
+        merge_default_properties(&mut self.event_properties, &defaults.event);
+        merge_default_properties(&mut self.session_properties, &defaults.session);
+    }
+}
+
+// id of the previous event a track event updates, if it carries a non-empty "woopra_update_id"
+pub(crate) fn update_id(data: &TrackData) -> Option<String> {
+    data.properties
+        .iter()
+        .rev()
+        .find(|(key, value)| key == UPDATE_PROPERTY && !value.trim().is_empty())
+        .map(|(_, value)| value.trim().to_string())
+}
+
+// this struct is only used with Track events updating a previous event: only the event
+// properties are updated, so the context of the update (page, visitor, session, ...) is not sent
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct WoopraPayloadUpdate {
+    project: String,
+    event: String,
+    id: String, // id of the updated event
+
+    // properties are prefixed with "ce_" (event) when serialized as a flattened map
+    #[serde(skip_serializing_if = "Properties::is_empty", flatten)]
+    event_properties: Properties<EventScope>,
+}
+
+impl WoopraPayloadUpdate {
+    pub(crate) fn new(project: String, event: String, id: String) -> Self {
+        WoopraPayloadUpdate {
+            project,
+            event,
+            id,
+            ..WoopraPayloadUpdate::default()
+        }
+    }
+
+    // this method adds the event properties of the track data, properties routed to the
+    // visitor or session scope and the reserved properties are not part of the update
+    pub(crate) fn add_track_properties(&mut self, data: &TrackData, routing: &ScopeRouting) {
+        for (key, value) in data.properties.iter() {
+            if key == TIMEOUT_PROPERTY || key == UPDATE_PROPERTY {
+                continue;
+            }
+            let key = match routing.route(key) {
+                Some((PropertyScope::Event, key)) => key,
+                Some(_) => continue,
+                None if key.starts_with(SessionScope::PREFIX) => continue,
+                None => key,
+            };
+            self.event_properties
+                .push(key.to_string(), value.to_string());
+        }
+    }
+
+    // this method resolves the values competing for the same key (with or without prefix),
+    // and lists them in the "ce_property_collisions" warning property
+    pub(crate) fn resolve_collisions(&mut self, policy: CollisionPolicy) {
+        let collisions = self.event_properties.resolve_collisions(policy);
+        if !collisions.is_empty() {
+            self.event_properties
+                .insert(COLLISIONS_PROPERTY.to_string(), collisions.join(","));
+        }
+    }
+
+    // this method applies PII scrubbing to the event properties
+    // (project, event and the updated event id are required and left untouched)
+    pub(crate) fn scrub_pii(&mut self, scrubbing: &PiiScrubbing) {
+        if !scrubbing.is_enabled() {
+            return;
+        }
+        scrub_properties(&mut self.event_properties, scrubbing);
+    }
+}
+
+// documentation: https://docs.woopra.com/reference/track-identify
+// this struct is only used with User events
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct WoopraPayloadIdentify {
+    // this is the only required field
+    project: String,
+
+    // visitor properties are prefixed with "cv_" (visitor)
+    // when serialized as a flattened map
+    #[serde(skip_serializing_if = "Properties::is_empty", flatten)]
+    visitor_properties: Properties<VisitorScope>,
+
+    // default identifier (could be cv_email too)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cv_id: Option<String>,
+
+    // optional cookie id (required only if no other identifier is provided)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cookie: Option<String>,
+}
+
+impl WoopraPayload for WoopraPayloadTrack {
+    fn set_project(&mut self, project: &str) {
+        self.project = project.to_string();
+    }
+}
+
+impl WoopraPayload for WoopraPayloadUpdate {
+    fn set_project(&mut self, project: &str) {
+        self.project = project.to_string();
+    }
+}
+
+impl WoopraPayload for WoopraPayloadIdentify {
+    fn set_project(&mut self, project: &str) {
+        self.project = project.to_string();
+    }
+}
+
+impl WoopraPayloadIdentify {
+    pub(crate) fn new(edgee_event: &Event, project: String) -> anyhow::Result<Self> {
+        let mut payload = WoopraPayloadIdentify {
+            project,
+            ..WoopraPayloadIdentify::default()
+        };
+
+        // geo ip, set before the user properties so that a "country" property collides with it
+        if !edgee_event.context.client.country_code.is_empty() {
+            payload.visitor_properties.insert(
+                "country".to_string(),
+                edgee_event.context.client.country_code.clone(),
+            );
+        }
+
+        // add properties from context.user
+        payload.add_user_properties(&edgee_event.context.user);
+
+        Ok(payload)
+    }
+
+    // this method can be used to add user properties to the payload (from event.data or context.user)
+    pub(crate) fn add_user_properties(&mut self, user: &crate::data_collection::UserData) {
+        if !user.anonymous_id.is_empty() {
+            self.cv_id = Some(user.anonymous_id.clone());
+        }
+        if !user.user_id.is_empty() {
+            // overwrite the anonymous_id with user_id if available
+            self.cv_id = Some(user.user_id.clone());
+        }
+
+        // user properties
+        if !user.properties.is_empty() {
+            for (key, value) in user.properties.clone().iter() {
+                self.visitor_properties.push(key.to_string(), value.clone());
+            }
+        }
+    }
+}
+
+impl WoopraPayloadIdentify {
+    // this method resolves the values competing for the same key (with or without prefix),
+    // and lists them in the "cv_property_collisions" warning property;
+    // an "id" property is sent under the key of the identifier (cv_id): it identifies the
+    // visitor when there is no user id, otherwise the identifier is kept and it collides
+    pub(crate) fn resolve_collisions(&mut self, policy: CollisionPolicy) {
+        let mut collisions = self.visitor_properties.resolve_collisions(policy);
+        if let Some(id) = self.visitor_properties.take("id") {
+            match self.cv_id.as_ref() {
+                None => self.cv_id = Some(id),
+                Some(cv_id) if *cv_id == id => {}
+                Some(_) => {
+                    if !collisions.iter().any(|collision| collision == "cv_id") {
+                        collisions.push("cv_id".to_string());
+                    }
+                    if policy == CollisionPolicy::Rename {
+                        let renamed = self.visitor_properties.renamed("id");
+                        self.visitor_properties.insert(renamed, id);
+                    }
+                }
+            }
+        }
+        if !collisions.is_empty() {
+            self.visitor_properties
+                .insert(COLLISIONS_PROPERTY.to_string(), collisions.join(","));
+        }
+    }
+
+    // this method tags the visitor with the sample rate the event was kept with, the mapping
+    // owns this key (a user property with the same name is replaced)
+    pub(crate) fn add_sample_rate(&mut self, rate: f64) {
+        self.visitor_properties.remove("sample_rate");
+        self.visitor_properties
+            .insert("sample_rate".to_string(), rate.to_string());
+    }
+
+    // this method adds the static default visitor properties from settings,
+    // values supplied by the event always take precedence
+    pub(crate) fn add_default_properties(&mut self, defaults: &DefaultProperties) {
+        merge_default_properties(&mut self.visitor_properties, &defaults.visitor);
+    }
+
+    // this method replaces the visitor id and the selected user properties with their keyed hash
+    pub(crate) fn hash_identifiers(&mut self, hashing: &IdentifierHashing) {
+        if !hashing.is_enabled() {
+            return;
+        }
+        if let Some(cv_id) = self.cv_id.as_mut() {
+            *cv_id = hashing.hash(cv_id);
+        }
+        for (key, value) in self.visitor_properties.iter_mut() {
+            if hashing.is_hashed_property(key) {
+                *value = hashing.hash(value);
+            }
+        }
+    }
+
+    // this method applies PII scrubbing to every visitor property and identifier
+    pub(crate) fn scrub_pii(&mut self, scrubbing: &PiiScrubbing) {
+        if !scrubbing.is_enabled() {
+            return;
+        }
+        scrub_properties(&mut self.visitor_properties, scrubbing);
+        scrub_field(&mut self.cv_id, "cv_id", scrubbing);
+        scrub_field(&mut self.cookie, "cookie", scrubbing);
+    }
+}
+
+// Helper function to add default properties that are not already set (with or without prefix)
+fn merge_default_properties<S: Scope>(
+    properties: &mut Properties<S>,
+    defaults: &[(String, String)],
+) {
+    for (key, value) in defaults {
+        if !properties.contains(key) {
+            properties.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+// Helper function to scrub a property map, using the prefixed key as field name
+fn scrub_properties<S: Scope>(properties: &mut Properties<S>, scrubbing: &PiiScrubbing) {
+    properties.retain(
+        |key, value| match scrubbing.scrub(&Properties::<S>::prefixed(key), value) {
+            Some(scrubbed) => {
+                *value = scrubbed;
+                true
+            }
+            None => false,
+        },
+    );
+}
+
+// Helper function to scrub an optional field
+fn scrub_field(field: &mut Option<String>, name: &str, scrubbing: &PiiScrubbing) {
+    if let Some(value) = field.take() {
+        *field = scrubbing.scrub(name, &value);
+    }
+}
@@ -0,0 +1,131 @@
+// golden snapshot suite: each directory in tests/golden holds an Edgee event (event.json), the
+// component settings (settings.json) and the expected request with its decoded Woopra parameters
+// (expected.json); run with UPDATE_GOLDEN=1 to rewrite the expected files after a mapping change
+use pretty_assertions::StrComparison;
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use woopra_core::data_collection::{EdgeeRequest, Event, HttpMethod};
+use woopra_core::{DecodedRequest, Settings};
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn read_json(path: &Path) -> Value {
+    let content = fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("failed to read {}: {err}", path.display()));
+    serde_json::from_str(&content)
+        .unwrap_or_else(|err| panic!("invalid JSON in {}: {err}", path.display()))
+}
+
+fn run_case(dir: &Path) -> Value {
+    let settings = read_json(&dir.join("settings.json"))
+        .as_object()
+        .expect("settings.json must be an object")
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            (key.clone(), value)
+        })
+        .collect();
+    let event: Event = serde_json::from_value(read_json(&dir.join("event.json")))
+        .unwrap_or_else(|err| panic!("invalid event in {}: {err}", dir.display()));
+
+    let request =
+        Settings::new(settings).and_then(|settings| woopra_core::build_request(&event, &settings));
+    match request {
+        Ok(request) => snapshot(&request),
+        Err(err) => json!({ "error": err.to_string() }),
+    }
+}
+
+// the query strings are not snapshotted as is since their parameter order is not stable,
+// only their decoded parameters (for a fan-out request, one entry per mirrored URL)
+fn snapshot(request: &EdgeeRequest) -> Value {
+    let method = match request.method {
+        HttpMethod::Head => "HEAD",
+        HttpMethod::Get => "GET",
+        HttpMethod::Put => "PUT",
+        HttpMethod::Post => "POST",
+        HttpMethod::Delete => "DELETE",
+    };
+    let (location, _) = request
+        .url
+        .split_once('?')
+        .unwrap_or((request.url.as_str(), ""));
+    let headers: Map<String, Value> = request
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), json!(value)))
+        .collect();
+
+    let woopra: Vec<Value> = match woopra_core::decode_url(&request.url) {
+        Ok(decoded) => vec![decoded_to_json(&decoded)],
+        Err(_) => request
+            .body
+            .lines()
+            .map(|url| decoded_to_json(&woopra_core::decode_url(url).unwrap()))
+            .collect(),
+    };
+
+    json!({
+        "method": method,
+        "url": location,
+        "headers": headers,
+        "forward_client_headers": request.forward_client_headers,
+        "woopra": woopra,
+    })
+}
+
+fn decoded_to_json(decoded: &DecodedRequest) -> Value {
+    json!({
+        "endpoint": decoded.endpoint,
+        "params": decoded.params,
+        "visitor": decoded.visitor,
+        "event": decoded.event,
+        "session": decoded.session,
+    })
+}
+
+#[test]
+fn golden_snapshots() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut cases: Vec<PathBuf> = fs::read_dir(golden_dir())
+        .expect("missing tests/golden directory")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty(), "no golden cases found");
+
+    let mut failures = Vec::new();
+    for dir in cases.iter() {
+        let name = dir.file_name().unwrap().to_string_lossy().to_string();
+        let actual = serde_json::to_string_pretty(&run_case(dir)).unwrap() + "\n";
+        let expected_path = dir.join("expected.json");
+
+        if update {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+        if expected != actual {
+            failures.push(format!(
+                "--- {name} ---\n{}",
+                StrComparison::new(&expected, &actual)
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} golden cases differ (run with UPDATE_GOLDEN=1 to accept the changes):\n\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n")
+    );
+}
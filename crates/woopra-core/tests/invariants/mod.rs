@@ -0,0 +1,417 @@
+// invariants of the mapping that hold for any event, shared by the property-based tests and the
+// fuzz target: the request URL is a valid Woopra URL, and every input property is either present
+// in it or accounted for (by a reported collision with another property, a reserved name or a filter)
+use std::collections::BTreeMap;
+use woopra_core::data_collection::{Data, Dict, EdgeeRequest, Event, TrackData};
+use woopra_core::{CollisionPolicy, DecodedRequest};
+
+const TIMEOUT_PROPERTY: &str = "woopra_timeout";
+const UPDATE_PROPERTY: &str = "woopra_update_id";
+const EVENT_ID_PROPERTY: &str = "edgee_event_id";
+const COLLISIONS_PROPERTY: &str = "property_collisions";
+const REDACTED: &str = "[REDACTED]";
+
+// the PII scrubbing configured for the run, which can alter or remove property values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    None,
+    Redact,
+    Drop,
+}
+
+impl Filter {
+    pub fn settings(&self) -> Vec<(String, String)> {
+        let action = match self {
+            Filter::None => return vec![],
+            Filter::Redact => "redact",
+            Filter::Drop => "drop",
+        };
+        vec![("pii_action".to_string(), action.to_string())]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Scope {
+    Visitor,
+    Event,
+    Session,
+}
+
+impl Scope {
+    fn prefix(&self) -> &'static str {
+        match self {
+            Scope::Visitor => "cv_",
+            Scope::Event => "ce_",
+            Scope::Session => "cs_",
+        }
+    }
+}
+
+// values competing for each decoded key (whether the key was prefixed, the value) in the order
+// they are set, the visitor identifier of identify requests and the input properties
+#[derive(Default)]
+struct Model {
+    values: BTreeMap<(Scope, String), Vec<(bool, String)>>,
+    identifier: Option<String>,
+    inputs: Vec<(Scope, String, String)>,
+}
+
+impl Model {
+    // the identifier is sent in a dedicated field, the last non-empty user id
+    fn identifier(&mut self, value: &str) {
+        if !value.is_empty() {
+            self.identifier = Some(value.to_string());
+        }
+    }
+
+    fn builtin(&mut self, scope: Scope, key: &str, value: &str) {
+        if !value.is_empty() {
+            self.always(scope, key, value);
+        }
+    }
+
+    // a builtin property that is set even when empty; builtins are set before the input
+    // properties and replace each other
+    fn always(&mut self, scope: Scope, key: &str, value: &str) {
+        self.values
+            .insert((scope, key.to_string()), vec![(false, value.to_string())]);
+    }
+
+    // page and track properties routed to a scope
+    fn event_input(&mut self, raw_key: &str, value: &str) -> bool {
+        match route(raw_key) {
+            Some((scope, key)) => {
+                self.input(scope, key, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // an input property is added even if its key is set, unless it already holds the value
+    fn input(&mut self, scope: Scope, raw_key: &str, value: &str) {
+        let prefixed = raw_key.starts_with(scope.prefix());
+        let key = raw_key.strip_prefix(scope.prefix()).unwrap_or(raw_key);
+        let values = self.values.entry((scope, key.to_string())).or_default();
+        if !values.contains(&(prefixed, value.to_string())) {
+            values.push((prefixed, value.to_string()));
+        }
+        self.inputs
+            .push((scope, key.to_string(), value.to_string()));
+    }
+
+    fn visitor_properties(&mut self, properties: &Dict) {
+        for (key, value) in properties.iter() {
+            self.input(Scope::Visitor, key, value);
+        }
+    }
+
+    // the Woopra keys that several values competed for, on identify requests an "id" property
+    // also competes with the identifier
+    fn collisions(&self) -> Vec<String> {
+        self.values
+            .iter()
+            .filter(|((scope, key), values)| {
+                let identifier = match (scope, key.as_str(), &self.identifier) {
+                    (Scope::Visitor, "id", Some(id)) => values.iter().any(|(_, value)| value != id),
+                    _ => false,
+                };
+                values.len() > 1 || identifier
+            })
+            .map(|((scope, key), _)| format!("{}{key}", scope.prefix()))
+            .collect()
+    }
+}
+
+pub fn check(
+    event: &Event,
+    request: &EdgeeRequest,
+    filter: Filter,
+    policy: CollisionPolicy,
+) -> Result<(), String> {
+    let decoded = woopra_core::decode_url(&request.url)
+        .map_err(|err| format!("invalid request URL {}: {err}", request.url))?;
+    if decoded.project().unwrap_or_default().is_empty() {
+        return Err(format!("missing project in {}", request.url));
+    }
+
+    let context = &event.context;
+    let mut model = Model::default();
+    let mut timeouts = Vec::new();
+    let mut event_id_sent = false;
+    match &event.data {
+        Data::Page(_) | Data::Track(_) => {
+            let expected_event = match &event.data {
+                Data::Track(data) => data.name.as_str(),
+                _ => "pv",
+            };
+            if decoded.event_name() != Some(expected_event) {
+                return Err(format!(
+                    "expected event {expected_event} in {}",
+                    request.url
+                ));
+            }
+            // a track event carrying the id of a previous event updates it
+            let update_id = match &event.data {
+                Data::Track(data) => data
+                    .properties
+                    .iter()
+                    .rev()
+                    .find(|(key, value)| key == UPDATE_PROPERTY && !value.trim().is_empty())
+                    .map(|(_, value)| value.trim()),
+                _ => None,
+            };
+            if decoded.is_update() != update_id.is_some() {
+                return Err(format!(
+                    "expected update of event {update_id:?} in {}",
+                    request.url
+                ));
+            }
+            if let (Some(update_id), Data::Track(data)) = (update_id, &event.data) {
+                return check_update(&decoded, request, data, update_id, filter, policy);
+            }
+            if decoded.params.get("timestamp") != Some(&event.timestamp.to_string()) {
+                return Err(format!("missing timestamp in {}", request.url));
+            }
+            // other track requests are identified by the Edgee event uuid
+            let uuid = Some(event.uuid.as_str()).filter(|uuid| !uuid.is_empty());
+            if decoded.event_id() != update_id.or(uuid) {
+                return Err(format!("unexpected event id in {}", request.url));
+            }
+            if let (Some(uuid), None) = (uuid, update_id) {
+                if decoded.event.get(EVENT_ID_PROPERTY).map(String::as_str) != Some(uuid) {
+                    return Err(format!("missing event id property in {}", request.url));
+                }
+                event_id_sent = true;
+            }
+
+            model.builtin(Scope::Visitor, "anonymous_id", &context.user.anonymous_id);
+            model.builtin(Scope::Visitor, "user_id", &context.user.user_id);
+            model.builtin(Scope::Visitor, "country", &context.client.country_code);
+            model.builtin(Scope::Event, "title", &context.page.title);
+            model.builtin(Scope::Event, "uri", &context.page.url);
+            model.always(Scope::Session, "session_id", &context.session.session_id);
+            model.always(
+                Scope::Session,
+                "session_count",
+                &context.session.session_count.to_string(),
+            );
+            if let Data::Page(data) = &event.data {
+                model.builtin(Scope::Event, "title", &data.title);
+                model.builtin(Scope::Event, "uri", &data.url);
+            }
+            page_properties(&mut model, &mut timeouts, &context.page.properties);
+            model.visitor_properties(&context.user.properties);
+
+            match &event.data {
+                Data::Page(data) => {
+                    page_properties(&mut model, &mut timeouts, &data.properties);
+                }
+                Data::Track(data) => {
+                    for (key, value) in data.properties.iter() {
+                        if key == TIMEOUT_PROPERTY {
+                            timeouts.push(value.clone());
+                            continue;
+                        }
+                        if key == UPDATE_PROPERTY {
+                            continue;
+                        }
+                        if model.event_input(key, value) {
+                            continue;
+                        }
+                        model.input(Scope::Event, key, value);
+                    }
+                }
+                Data::User(_) => unreachable!(),
+            }
+        }
+        Data::User(data) => {
+            if !decoded.is_identify() {
+                return Err(format!("expected an identify request: {}", request.url));
+            }
+            model.builtin(Scope::Visitor, "country", &context.client.country_code);
+            for user in [&context.user, data] {
+                model.identifier(&user.anonymous_id);
+                model.identifier(&user.user_id);
+                model.visitor_properties(&user.properties);
+            }
+        }
+    }
+
+    // an override is only applied if it is a positive number of milliseconds
+    let valid_timeout = timeouts
+        .iter()
+        .any(|value| value.trim().parse::<u64>().is_ok_and(|timeout| timeout > 0));
+    if valid_timeout && filter == Filter::None && !decoded.params.contains_key("timeout") {
+        return Err(format!("missing timeout override in {}", request.url));
+    }
+
+    // the warning property lists the collisions, it is sent in the event scope of track requests
+    // and in the visitor scope of identify requests
+    let warning_scope = match event.data {
+        Data::User(_) => Scope::Visitor,
+        _ => Scope::Event,
+    };
+    // the event id property is reserved when the event id is sent
+    let reserved = match event_id_sent {
+        true => vec![(Scope::Event, EVENT_ID_PROPERTY)],
+        false => vec![],
+    };
+    check_properties(
+        &model,
+        &decoded,
+        request,
+        warning_scope,
+        &reserved,
+        filter,
+        policy,
+    )
+}
+
+// update requests only carry the project, the event name, the id of the updated event and the
+// event properties of the track data
+fn check_update(
+    decoded: &DecodedRequest,
+    request: &EdgeeRequest,
+    data: &TrackData,
+    update_id: &str,
+    filter: Filter,
+    policy: CollisionPolicy,
+) -> Result<(), String> {
+    if decoded.event_id() != Some(update_id) {
+        return Err(format!("unexpected updated event id in {}", request.url));
+    }
+    if let Some(param) = decoded
+        .params
+        .keys()
+        .find(|param| !["project", "event", "id"].contains(&param.as_str()))
+    {
+        return Err(format!("unexpected {param} in update {}", request.url));
+    }
+    if !decoded.visitor.is_empty() || !decoded.session.is_empty() {
+        return Err(format!("unexpected context in update {}", request.url));
+    }
+
+    let mut model = Model::default();
+    for (key, value) in data.properties.iter() {
+        if key == TIMEOUT_PROPERTY || key == UPDATE_PROPERTY {
+            continue;
+        }
+        // properties routed to the visitor or session scope are not updated
+        match route(key) {
+            Some((Scope::Event, key)) => model.input(Scope::Event, key, value),
+            Some(_) => {}
+            None => model.input(Scope::Event, key, value),
+        }
+    }
+    check_properties(&model, decoded, request, Scope::Event, &[], filter, policy)
+}
+
+// every input property is sent, or accounted for by a reported collision, a reserved name
+// or the PII filter
+fn check_properties(
+    model: &Model,
+    decoded: &DecodedRequest,
+    request: &EdgeeRequest,
+    warning_scope: Scope,
+    reserved: &[(Scope, &str)],
+    filter: Filter,
+    policy: CollisionPolicy,
+) -> Result<(), String> {
+    let collisions = model.collisions();
+    let reported: Vec<&str> = scope_properties(decoded, warning_scope)
+        .get(COLLISIONS_PROPERTY)
+        .map(|warning| warning.split(',').collect())
+        .unwrap_or_default();
+    if filter == Filter::None {
+        if let Some(missing) = collisions
+            .iter()
+            .find(|collision| !reported.contains(&collision.as_str()))
+        {
+            return Err(format!(
+                "collision on {missing} not reported in {}",
+                request.url
+            ));
+        }
+    }
+
+    for (scope, key, value) in model.inputs.iter() {
+        // the warning property is reserved when there are collisions
+        if *scope == warning_scope && key == COLLISIONS_PROPERTY && !collisions.is_empty() {
+            continue;
+        }
+        if reserved.contains(&(*scope, key.as_str())) {
+            continue;
+        }
+        let properties = scope_properties(decoded, *scope);
+        let actual = properties.get(key);
+        // renamed values are sent as "key_2", "key_3", ...
+        let renamed = policy == CollisionPolicy::Rename
+            && properties.iter().any(|(renamed, actual)| {
+                actual == value
+                    && renamed
+                        .strip_prefix(key.as_str())
+                        .and_then(|suffix| suffix.strip_prefix('_'))
+                        .is_some_and(|suffix| suffix.parse::<u32>().is_ok())
+            });
+        // a value is only left out when it lost a reported collision, never when renaming
+        // (unless the PII filter altered the renamed value)
+        let collided = (policy != CollisionPolicy::Rename || filter != Filter::None)
+            && reported.contains(&format!("{}{key}", scope.prefix()).as_str());
+        let accounted = match (actual, filter) {
+            _ if renamed || collided => true,
+            (Some(actual), _) if actual == value => true,
+            (Some(actual), Filter::Redact) => actual.contains(REDACTED),
+            (None, Filter::Drop) => true,
+            _ => false,
+        };
+        if !accounted {
+            return Err(format!(
+                "property {key:?} = {value:?} ({scope:?}) is {actual:?} in {}",
+                request.url
+            ));
+        }
+    }
+    Ok(())
+}
+
+// page and track properties are routed to a scope by a "visitor.", "event." or "session."
+// namespace, or to the session scope when prefixed with "cs_"
+fn route(raw_key: &str) -> Option<(Scope, &str)> {
+    let namespaces = [
+        ("visitor.", Scope::Visitor),
+        ("event.", Scope::Event),
+        ("session.", Scope::Session),
+    ];
+    for (namespace, scope) in namespaces {
+        match raw_key.strip_prefix(namespace) {
+            Some(key) if !key.is_empty() => return Some((scope, key)),
+            _ => {}
+        }
+    }
+    raw_key
+        .starts_with(Scope::Session.prefix())
+        .then_some((Scope::Session, raw_key))
+}
+
+fn page_properties(model: &mut Model, timeouts: &mut Vec<String>, properties: &Dict) {
+    for (key, value) in properties.iter() {
+        if key == TIMEOUT_PROPERTY {
+            timeouts.push(value.clone());
+            continue;
+        }
+        if model.event_input(key, value) {
+            continue;
+        }
+        let key = format!("page_{}", key.replace(' ', "_"));
+        model.input(Scope::Event, &key, value);
+    }
+}
+
+fn scope_properties(decoded: &DecodedRequest, scope: Scope) -> &BTreeMap<String, String> {
+    match scope {
+        Scope::Visitor => &decoded.visitor,
+        Scope::Event => &decoded.event,
+        Scope::Session => &decoded.session,
+    }
+}
@@ -0,0 +1,248 @@
+// end-to-end tests: the requests built by the mapping are sent to an in-process Woopra stand-in
+mod support;
+
+use pretty_assertions::assert_eq;
+use support::{MockWoopra, FANOUT_PATH};
+use woopra_core::data_collection::{
+    Context, Data, EdgeeRequest, Event, EventType, HttpMethod, PageData, TrackData, UserData,
+};
+use woopra_core::Settings;
+
+fn settings(extra: &[(&str, &str)]) -> Settings {
+    let mut settings_dict = vec![("project_name".to_string(), "example.com".to_string())];
+    settings_dict.extend(
+        extra
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string())),
+    );
+    Settings::new(settings_dict).unwrap()
+}
+
+fn sample_context() -> Context {
+    let mut context = Context::default();
+    context.page.url = "https://www.example.com/home".to_string();
+    context.page.title = "Home".to_string();
+    context.user.user_id = "123".to_string();
+    context.user.anonymous_id = "456".to_string();
+    context.client.ip = "192.168.0.1".to_string();
+    context.session.session_id = "s-1".to_string();
+    context
+}
+
+fn sample_event(data: Data) -> Event {
+    let event_type = match data {
+        Data::Page(_) => EventType::Page,
+        Data::Track(_) => EventType::Track,
+        Data::User(_) => EventType::User,
+    };
+    Event {
+        uuid: "0f8fad5b-d9cb-469f-a165-70867728950e".to_string(),
+        timestamp: 1700003600,
+        timestamp_millis: 1700003600000,
+        timestamp_micros: 1700003600000000,
+        event_type,
+        data,
+        context: sample_context(),
+        consent: None,
+    }
+}
+
+fn sample_page_event() -> Event {
+    sample_event(Data::Page(sample_context().page))
+}
+
+fn sample_track_event() -> Event {
+    sample_event(Data::Track(TrackData {
+        name: "Signup".to_string(),
+        properties: vec![("plan".to_string(), "pro".to_string())],
+        products: vec![],
+    }))
+}
+
+fn sample_user_event() -> Event {
+    sample_event(Data::User(UserData {
+        user_id: "123".to_string(),
+        anonymous_id: "456".to_string(),
+        edgee_id: "abc".to_string(),
+        properties: vec![("email".to_string(), "jane@example.com".to_string())],
+    }))
+}
+
+#[test]
+fn page_track_and_user_requests_are_accepted() {
+    let woopra = MockWoopra::start();
+    let settings = settings(&[]);
+
+    for event in [
+        sample_page_event(),
+        sample_track_event(),
+        sample_user_event(),
+    ] {
+        let request = woopra_core::build_request(&event, &settings).unwrap();
+        assert_eq!(woopra.send(&request), 200);
+    }
+
+    let requests = woopra.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].path, "/track/ce");
+    assert_eq!(requests[0].param("event"), Some("pv"));
+    assert_eq!(requests[0].param("ce_title"), Some("Home"));
+    assert_eq!(requests[1].path, "/track/ce");
+    assert_eq!(requests[1].param("event"), Some("Signup"));
+    assert_eq!(requests[1].param("ce_plan"), Some("pro"));
+    assert_eq!(requests[2].path, "/track/identify");
+    assert_eq!(requests[2].param("cv_email"), Some("jane@example.com"));
+    for request in requests.iter() {
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.param("project"), Some("example.com"));
+        assert_eq!(request.status, 200);
+    }
+}
+
+#[test]
+fn mirrored_requests_reach_every_project() {
+    let woopra = MockWoopra::start();
+    let fanout_endpoint = woopra.url(FANOUT_PATH);
+    let settings = settings(&[
+        ("mirror_projects", "staging.example.com,backup.example.com"),
+        ("mirror_fanout_endpoint", &fanout_endpoint),
+    ]);
+
+    let request = woopra_core::build_request(&sample_track_event(), &settings).unwrap();
+    assert_eq!(woopra.send(&request), 200);
+
+    let requests = woopra.requests();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, FANOUT_PATH);
+    let projects: Vec<_> = requests[1..]
+        .iter()
+        .map(|request| request.param("project").unwrap())
+        .collect();
+    assert_eq!(
+        projects,
+        vec!["example.com", "staging.example.com", "backup.example.com"]
+    );
+    assert_eq!(
+        requests[1..].iter().all(|request| request.status == 200),
+        true
+    );
+}
+
+#[test]
+fn update_requests_are_accepted() {
+    let woopra = MockWoopra::start();
+    let mut event = sample_track_event();
+    if let Data::Track(ref mut data) = event.data {
+        data.properties = vec![
+            ("woopra_update_id".to_string(), "3f2a9c".to_string()),
+            ("status".to_string(), "confirmed".to_string()),
+        ];
+    }
+
+    let request = woopra_core::build_request(&event, &settings(&[])).unwrap();
+    assert_eq!(woopra.send(&request), 200);
+
+    let received = woopra.requests().remove(0);
+    assert_eq!(received.path, "/track/update");
+    assert_eq!(received.param("id"), Some("3f2a9c"));
+    assert_eq!(received.param("event"), Some("Signup"));
+    assert_eq!(received.param("ce_status"), Some("confirmed"));
+}
+
+#[test]
+fn simulated_errors_are_returned_and_recorded() {
+    let woopra = MockWoopra::start();
+    let settings = settings(&[]);
+    let request = woopra_core::build_request(&sample_track_event(), &settings).unwrap();
+
+    woopra.fail_next(503);
+    assert_eq!(woopra.send(&request), 503);
+    assert_eq!(woopra.send(&request), 200);
+
+    let statuses: Vec<_> = woopra
+        .requests()
+        .iter()
+        .map(|request| request.status)
+        .collect();
+    assert_eq!(statuses, vec![503, 200]);
+
+    // the retry carries the same event id
+    let ids: Vec<_> = woopra
+        .requests()
+        .iter()
+        .map(|request| request.param("id").unwrap().to_string())
+        .collect();
+    assert_eq!(ids, vec!["0f8fad5b-d9cb-469f-a165-70867728950e"; 2]);
+}
+
+#[test]
+fn requests_missing_required_params_are_rejected() {
+    let woopra = MockWoopra::start();
+    let request = |url: &str| EdgeeRequest {
+        method: HttpMethod::Get,
+        url: url.to_string(),
+        headers: vec![],
+        forward_client_headers: true,
+        body: String::new(),
+    };
+
+    assert_eq!(
+        woopra.send(&request("https://www.woopra.com/track/ce?event=pv")),
+        400
+    );
+    assert_eq!(
+        woopra.send(&request(
+            "https://www.woopra.com/track/ce?project=example.com"
+        )),
+        400
+    );
+    assert_eq!(
+        woopra.send(&request(
+            "https://www.woopra.com/track/identify?project=example.com"
+        )),
+        400
+    );
+    assert_eq!(
+        woopra.send(&request(
+            "https://www.woopra.com/track/update?project=example.com&event=pv"
+        )),
+        400
+    );
+    assert_eq!(
+        woopra.send(&request(
+            "https://www.woopra.com/track/other?project=example.com"
+        )),
+        404
+    );
+}
+
+#[test]
+fn page_request_fields_survive_the_round_trip() {
+    let woopra = MockWoopra::start();
+    let mut event = sample_page_event();
+    if let Data::Page(ref mut data) = event.data {
+        *data = PageData {
+            url: "https://www.example.com/search?q=a b&c=d".to_string(),
+            title: "Résultats & co".to_string(),
+            ..sample_context().page
+        };
+    }
+
+    let request = woopra_core::build_request(&event, &settings(&[])).unwrap();
+    assert_eq!(woopra.send(&request), 200);
+
+    let received = woopra.requests().remove(0);
+    assert_eq!(
+        received.param("ce_uri"),
+        Some("https://www.example.com/search?q=a b&c=d")
+    );
+    assert_eq!(received.param("ce_title"), Some("Résultats & co"));
+    assert_eq!(received.param("ip"), Some("192.168.0.1"));
+    assert_eq!(
+        received
+            .headers
+            .iter()
+            .any(|(name, value)| name.eq_ignore_ascii_case("content-length") && value == "0"),
+        true
+    );
+}
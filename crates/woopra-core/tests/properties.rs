@@ -0,0 +1,182 @@
+// property-based tests of the mapping: arbitrary events, including property names that are
+// already prefixed, collide with each other or contain special characters
+mod invariants;
+
+use invariants::Filter;
+use proptest::prelude::*;
+use woopra_core::data_collection::{
+    Context, Data, Dict, Event, EventType, PageData, TrackData, UserData,
+};
+use woopra_core::{CollisionPolicy, Settings};
+
+// property names the mapping gives a meaning to, with and without scope prefixes
+const TRICKY_KEYS: &[&str] = &[
+    "id",
+    "user_id",
+    "anonymous_id",
+    "country",
+    "title",
+    "uri",
+    "session_id",
+    "woopra_timeout",
+    "woopra_update_id",
+    "edgee_event_id",
+    "project",
+    "event",
+    "timeout",
+    "property_collisions",
+];
+
+fn key() -> impl Strategy<Value = String> {
+    prop_oneof![
+        (
+            prop::sample::select(vec![
+                "",
+                "cv_",
+                "ce_",
+                "cs_",
+                "visitor.",
+                "visitor.cv_",
+                "event.",
+                "session.",
+                "session.cs_",
+            ]),
+            prop::sample::select(TRICKY_KEYS)
+        )
+            .prop_map(|(prefix, key)| format!("{prefix}{key}")),
+        "(visitor\\.|event\\.|session\\.)?(cv_|ce_|cs_)?[a-z]{1,3}",
+        "[a-zA-Z0-9 _.\\[\\]&=%+#?-]{0,10}",
+        "\\PC{0,8}",
+    ]
+}
+
+fn value() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "\\PC{0,16}",
+        "[0-9]{1,7}",
+        Just("jane@example.com".to_string()),
+        Just("call +1 (555) 123-4567".to_string()),
+    ]
+}
+
+fn properties() -> impl Strategy<Value = Dict> {
+    prop::collection::vec((key(), value()), 0..8)
+}
+
+fn context() -> impl Strategy<Value = Context> {
+    (
+        "[a-z0-9]{0,6}",
+        "[a-z0-9]{0,6}",
+        properties(),
+        "[A-Z]{0,2}",
+        "\\PC{0,12}",
+        "https://[a-z]{1,8}\\.com/[a-z?=&#]{0,10}",
+    )
+        .prop_map(|(user_id, anonymous_id, properties, country, title, url)| {
+            let mut context = Context::default();
+            context.user.user_id = user_id;
+            context.user.anonymous_id = anonymous_id;
+            context.user.properties = properties;
+            context.client.country_code = country;
+            context.page.title = title;
+            context.page.url = url;
+            context
+        })
+}
+
+fn data() -> impl Strategy<Value = Data> {
+    prop_oneof![
+        (properties(), "\\PC{0,12}").prop_map(|(properties, title)| Data::Page(PageData {
+            title,
+            properties,
+            ..PageData::default()
+        })),
+        ("\\PC{1,12}", properties()).prop_map(|(name, properties)| Data::Track(TrackData {
+            name,
+            properties,
+            products: vec![],
+        })),
+        ("[a-z0-9]{0,6}", "[a-z0-9]{0,6}", properties()).prop_map(
+            |(user_id, anonymous_id, properties)| Data::User(UserData {
+                user_id,
+                anonymous_id,
+                edgee_id: String::new(),
+                properties,
+            })
+        ),
+    ]
+}
+
+fn event() -> impl Strategy<Value = Event> {
+    (
+        data(),
+        context(),
+        0..2_000_000_000i64,
+        "([0-9a-f]{8}-[0-9a-f]{4})?",
+    )
+        .prop_map(|(data, context, timestamp, uuid)| {
+            let event_type = match data {
+                Data::Page(_) => EventType::Page,
+                Data::Track(_) => EventType::Track,
+                Data::User(_) => EventType::User,
+            };
+            Event {
+                uuid,
+                timestamp,
+                timestamp_millis: timestamp * 1000,
+                timestamp_micros: timestamp * 1_000_000,
+                event_type,
+                data,
+                context,
+                consent: None,
+            }
+        })
+}
+
+fn filter() -> impl Strategy<Value = Filter> {
+    prop::sample::select(vec![Filter::None, Filter::Redact, Filter::Drop])
+}
+
+fn policy() -> impl Strategy<Value = (CollisionPolicy, &'static str)> {
+    prop::sample::select(vec![
+        (CollisionPolicy::ExplicitPrefixWins, "explicit_prefix_wins"),
+        (CollisionPolicy::FirstWins, "first_wins"),
+        (CollisionPolicy::Rename, "rename"),
+    ])
+}
+
+fn settings(filter: Filter, policy: &str) -> Settings {
+    let mut settings_dict = vec![
+        ("project_name".to_string(), "example.com".to_string()),
+        ("property_collision_policy".to_string(), policy.to_string()),
+    ];
+    settings_dict.extend(filter.settings());
+    Settings::new(settings_dict).unwrap()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn every_property_is_sent_or_accounted_for(
+        event in event(),
+        filter in filter(),
+        (policy, policy_setting) in policy(),
+    ) {
+        let request = woopra_core::build_request(&event, &settings(filter, policy_setting));
+        prop_assert!(request.is_ok(), "{:?}", request);
+        let result = invariants::check(&event, &request.unwrap(), filter, policy);
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+
+    #[test]
+    fn mapping_is_deterministic(event in event(), (_, policy_setting) in policy()) {
+        let settings = settings(Filter::None, policy_setting);
+        let first = woopra_core::build_request(&event, &settings).unwrap();
+        let second = woopra_core::build_request(&event, &settings).unwrap();
+        prop_assert_eq!(
+            woopra_core::decode_url(&first.url).unwrap(),
+            woopra_core::decode_url(&second.url).unwrap()
+        );
+    }
+}
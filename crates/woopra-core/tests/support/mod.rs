@@ -0,0 +1,219 @@
+// in-process stand-in for Woopra's tracking endpoints, so that the requests built by the mapping
+// can be sent end to end without the network
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tiny_http::{Header, Response, Server};
+use woopra_core::data_collection::{EdgeeRequest, HttpMethod};
+
+// path of the mirror mode fan-out endpoint: each line of the body is replayed as a GET request
+pub const FANOUT_PATH: &str = "/fanout";
+
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    pub method: String,
+    pub path: String,
+    pub params: BTreeMap<String, String>,
+    pub headers: Vec<(String, String)>,
+    pub status: u16,
+}
+
+impl ReceivedRequest {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    received: Vec<ReceivedRequest>,
+    // statuses returned instead of validating the next requests
+    failures: VecDeque<u16>,
+}
+
+pub struct MockWoopra {
+    server: Arc<Server>,
+    state: Arc<Mutex<State>>,
+    address: String,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockWoopra {
+    pub fn start() -> Self {
+        let server = Arc::new(Server::http("127.0.0.1:0").expect("failed to start mock server"));
+        let address = server.server_addr().to_ip().unwrap().to_string();
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let handle = {
+            let server = server.clone();
+            let state = state.clone();
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle_request(request, &state);
+                }
+            })
+        };
+
+        MockWoopra {
+            server,
+            state,
+            address,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.address)
+    }
+
+    pub fn fail_next(&self, status: u16) {
+        self.state.lock().unwrap().failures.push_back(status);
+    }
+
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    // sends the request to the mock server whatever its host, and returns the response status
+    pub fn send(&self, request: &EdgeeRequest) -> u16 {
+        let method = match request.method {
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Get => "GET",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Post => "POST",
+            HttpMethod::Delete => "DELETE",
+        };
+        let target = request_target(&request.url);
+
+        let mut raw = format!(
+            "{method} {target} HTTP/1.1\r\nhost: {}\r\nconnection: close\r\n",
+            self.address
+        );
+        for (name, value) in request.headers.iter() {
+            raw.push_str(&format!("{name}: {value}\r\n"));
+        }
+        if !has_header(&request.headers, "content-length") {
+            raw.push_str(&format!("content-length: {}\r\n", request.body.len()));
+        }
+        raw.push_str("\r\n");
+        raw.push_str(&request.body);
+
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        stream.write_all(raw.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        // "HTTP/1.1 200 OK"
+        response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .expect("invalid response from mock server")
+    }
+}
+
+impl Drop for MockWoopra {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn handle_request(mut request: tiny_http::Request, state: &Mutex<State>) {
+    let method = request.method().to_string();
+    let (path, querystring) = split_target(request.url());
+    let headers: Vec<(String, String)> = request
+        .headers()
+        .iter()
+        .map(|header| (header.field.to_string(), header.value.to_string()))
+        .collect();
+    let mut body = String::new();
+    let _ = request.as_reader().read_to_string(&mut body);
+
+    let mut state = state.lock().unwrap();
+    let (params, mut status) = validate(&method, &path, &querystring);
+    let mut replayed = Vec::new();
+    if let Some(failure) = state.failures.pop_front() {
+        status = failure;
+    } else if path == FANOUT_PATH {
+        // every mirrored URL is recorded as if Woopra had received it
+        status = if method == "POST" { 200 } else { 405 };
+        for url in body.lines() {
+            let (path, querystring) = split_target(request_target(url));
+            let (params, url_status) = validate("GET", &path, &querystring);
+            if url_status != 200 {
+                status = url_status;
+            }
+            replayed.push(ReceivedRequest {
+                method: String::from("GET"),
+                path,
+                params,
+                headers: Vec::new(),
+                status: url_status,
+            });
+        }
+    }
+    state.received.push(ReceivedRequest {
+        method,
+        path,
+        params,
+        headers,
+        status,
+    });
+    state.received.extend(replayed);
+    drop(state);
+
+    let response = Response::from_string(if status == 200 { "" } else { "error" })
+        .with_status_code(status)
+        .with_header(Header::from_bytes("content-type", "text/plain").unwrap());
+    let _ = request.respond(response);
+}
+
+// checks the parameters Woopra requires on each endpoint
+fn validate(method: &str, path: &str, querystring: &str) -> (BTreeMap<String, String>, u16) {
+    let Ok(params) = serde_qs::from_str::<BTreeMap<String, String>>(querystring) else {
+        return (BTreeMap::new(), 400);
+    };
+    let is_set = |name: &str| params.get(name).is_some_and(|value| !value.is_empty());
+
+    let status = match path {
+        FANOUT_PATH => 200,
+        _ if method != "GET" => 405,
+        "/track/ce" if is_set("project") && is_set("event") => 200,
+        "/track/update" if is_set("project") && is_set("event") && is_set("id") => 200,
+        "/track/identify"
+            if is_set("project")
+                && params
+                    .iter()
+                    .any(|(name, value)| name.starts_with("cv_") && !value.is_empty()) =>
+        {
+            200
+        }
+        "/track/ce" | "/track/update" | "/track/identify" => 400,
+        _ => 404,
+    };
+    (params, status)
+}
+
+// the path and query of an absolute URL
+fn request_target(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|index| &rest[index..]).unwrap_or("/"),
+        None => url,
+    }
+}
+
+fn split_target(target: &str) -> (String, String) {
+    let (path, querystring) = target.split_once('?').unwrap_or((target, ""));
+    (path.to_string(), querystring.to_string())
+}
+
+fn has_header(headers: &[(String, String)], name: &str) -> bool {
+    headers
+        .iter()
+        .any(|(header, _)| header.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,295 @@
+use crate::exports::edgee::components::data_collection as wit;
+use woopra_core::data_collection as woopra;
+
+impl From<wit::Event> for woopra::Event {
+    fn from(event: wit::Event) -> Self {
+        woopra::Event {
+            uuid: event.uuid,
+            timestamp: event.timestamp,
+            timestamp_millis: event.timestamp_millis,
+            timestamp_micros: event.timestamp_micros,
+            event_type: event.event_type.into(),
+            data: event.data.into(),
+            context: event.context.into(),
+            consent: event.consent.map(Into::into),
+        }
+    }
+}
+
+impl From<wit::EventType> for woopra::EventType {
+    fn from(event_type: wit::EventType) -> Self {
+        match event_type {
+            wit::EventType::Page => woopra::EventType::Page,
+            wit::EventType::Track => woopra::EventType::Track,
+            wit::EventType::User => woopra::EventType::User,
+        }
+    }
+}
+
+impl From<wit::Consent> for woopra::Consent {
+    fn from(consent: wit::Consent) -> Self {
+        match consent {
+            wit::Consent::Pending => woopra::Consent::Pending,
+            wit::Consent::Granted => woopra::Consent::Granted,
+            wit::Consent::Denied => woopra::Consent::Denied,
+        }
+    }
+}
+
+impl From<wit::Data> for woopra::Data {
+    fn from(data: wit::Data) -> Self {
+        match data {
+            wit::Data::Page(page) => woopra::Data::Page(page.into()),
+            wit::Data::Track(track) => woopra::Data::Track(track.into()),
+            wit::Data::User(user) => woopra::Data::User(user.into()),
+        }
+    }
+}
+
+impl From<wit::PageData> for woopra::PageData {
+    fn from(page: wit::PageData) -> Self {
+        woopra::PageData {
+            name: page.name,
+            category: page.category,
+            keywords: page.keywords,
+            title: page.title,
+            url: page.url,
+            path: page.path,
+            search: page.search,
+            referrer: page.referrer,
+            properties: page.properties,
+        }
+    }
+}
+
+impl From<wit::UserData> for woopra::UserData {
+    fn from(user: wit::UserData) -> Self {
+        woopra::UserData {
+            user_id: user.user_id,
+            anonymous_id: user.anonymous_id,
+            edgee_id: user.edgee_id,
+            properties: user.properties,
+        }
+    }
+}
+
+impl From<wit::TrackData> for woopra::TrackData {
+    fn from(track: wit::TrackData) -> Self {
+        woopra::TrackData {
+            name: track.name,
+            properties: track.properties,
+            products: track.products,
+        }
+    }
+}
+
+impl From<wit::Context> for woopra::Context {
+    fn from(context: wit::Context) -> Self {
+        woopra::Context {
+            page: context.page.into(),
+            user: context.user.into(),
+            client: context.client.into(),
+            campaign: context.campaign.into(),
+            session: context.session.into(),
+        }
+    }
+}
+
+impl From<wit::Client> for woopra::Client {
+    fn from(client: wit::Client) -> Self {
+        woopra::Client {
+            ip: client.ip,
+            locale: client.locale,
+            timezone: client.timezone,
+            user_agent: client.user_agent,
+            user_agent_architecture: client.user_agent_architecture,
+            user_agent_bitness: client.user_agent_bitness,
+            user_agent_full_version_list: client.user_agent_full_version_list,
+            user_agent_version_list: client.user_agent_version_list,
+            user_agent_mobile: client.user_agent_mobile,
+            user_agent_model: client.user_agent_model,
+            os_name: client.os_name,
+            os_version: client.os_version,
+            screen_width: client.screen_width,
+            screen_height: client.screen_height,
+            screen_density: client.screen_density,
+            continent: client.continent,
+            country_code: client.country_code,
+            country_name: client.country_name,
+            region: client.region,
+            city: client.city,
+        }
+    }
+}
+
+impl From<wit::Campaign> for woopra::Campaign {
+    fn from(campaign: wit::Campaign) -> Self {
+        woopra::Campaign {
+            name: campaign.name,
+            source: campaign.source,
+            medium: campaign.medium,
+            term: campaign.term,
+            content: campaign.content,
+            creative_format: campaign.creative_format,
+            marketing_tactic: campaign.marketing_tactic,
+        }
+    }
+}
+
+impl From<wit::Session> for woopra::Session {
+    fn from(session: wit::Session) -> Self {
+        woopra::Session {
+            session_id: session.session_id,
+            previous_session_id: session.previous_session_id,
+            session_count: session.session_count,
+            session_start: session.session_start,
+            first_seen: session.first_seen,
+            last_seen: session.last_seen,
+        }
+    }
+}
+
+impl From<woopra::EdgeeRequest> for wit::EdgeeRequest {
+    fn from(request: woopra::EdgeeRequest) -> Self {
+        wit::EdgeeRequest {
+            method: request.method.into(),
+            url: request.url,
+            headers: request.headers,
+            forward_client_headers: request.forward_client_headers,
+            body: request.body,
+        }
+    }
+}
+
+impl From<woopra::HttpMethod> for wit::HttpMethod {
+    fn from(method: woopra::HttpMethod) -> Self {
+        match method {
+            woopra::HttpMethod::Head => wit::HttpMethod::Head,
+            woopra::HttpMethod::Get => wit::HttpMethod::Get,
+            woopra::HttpMethod::Put => wit::HttpMethod::Put,
+            woopra::HttpMethod::Post => wit::HttpMethod::Post,
+            woopra::HttpMethod::Delete => wit::HttpMethod::Delete,
+        }
+    }
+}
+
+// the tests of the component build their events from the woopra-core fixtures
+#[cfg(test)]
+mod to_wit {
+    use super::{wit, woopra};
+
+    impl From<woopra::Event> for wit::Event {
+        fn from(event: woopra::Event) -> Self {
+            wit::Event {
+                uuid: event.uuid,
+                timestamp: event.timestamp,
+                timestamp_millis: event.timestamp_millis,
+                timestamp_micros: event.timestamp_micros,
+                event_type: match event.event_type {
+                    woopra::EventType::Page => wit::EventType::Page,
+                    woopra::EventType::Track => wit::EventType::Track,
+                    woopra::EventType::User => wit::EventType::User,
+                },
+                data: event.data.into(),
+                context: event.context.into(),
+                consent: event.consent.map(|consent| match consent {
+                    woopra::Consent::Pending => wit::Consent::Pending,
+                    woopra::Consent::Granted => wit::Consent::Granted,
+                    woopra::Consent::Denied => wit::Consent::Denied,
+                }),
+            }
+        }
+    }
+
+    impl From<woopra::Data> for wit::Data {
+        fn from(data: woopra::Data) -> Self {
+            match data {
+                woopra::Data::Page(page) => wit::Data::Page(page.into()),
+                woopra::Data::Track(track) => wit::Data::Track(wit::TrackData {
+                    name: track.name,
+                    properties: track.properties,
+                    products: track.products,
+                }),
+                woopra::Data::User(user) => wit::Data::User(user.into()),
+            }
+        }
+    }
+
+    impl From<woopra::PageData> for wit::PageData {
+        fn from(page: woopra::PageData) -> Self {
+            wit::PageData {
+                name: page.name,
+                category: page.category,
+                keywords: page.keywords,
+                title: page.title,
+                url: page.url,
+                path: page.path,
+                search: page.search,
+                referrer: page.referrer,
+                properties: page.properties,
+            }
+        }
+    }
+
+    impl From<woopra::UserData> for wit::UserData {
+        fn from(user: woopra::UserData) -> Self {
+            wit::UserData {
+                user_id: user.user_id,
+                anonymous_id: user.anonymous_id,
+                edgee_id: user.edgee_id,
+                properties: user.properties,
+            }
+        }
+    }
+
+    impl From<woopra::Context> for wit::Context {
+        fn from(context: woopra::Context) -> Self {
+            let client = context.client;
+            let campaign = context.campaign;
+            let session = context.session;
+            wit::Context {
+                page: context.page.into(),
+                user: context.user.into(),
+                client: wit::Client {
+                    ip: client.ip,
+                    locale: client.locale,
+                    timezone: client.timezone,
+                    user_agent: client.user_agent,
+                    user_agent_architecture: client.user_agent_architecture,
+                    user_agent_bitness: client.user_agent_bitness,
+                    user_agent_full_version_list: client.user_agent_full_version_list,
+                    user_agent_version_list: client.user_agent_version_list,
+                    user_agent_mobile: client.user_agent_mobile,
+                    user_agent_model: client.user_agent_model,
+                    os_name: client.os_name,
+                    os_version: client.os_version,
+                    screen_width: client.screen_width,
+                    screen_height: client.screen_height,
+                    screen_density: client.screen_density,
+                    continent: client.continent,
+                    country_code: client.country_code,
+                    country_name: client.country_name,
+                    region: client.region,
+                    city: client.city,
+                },
+                campaign: wit::Campaign {
+                    name: campaign.name,
+                    source: campaign.source,
+                    medium: campaign.medium,
+                    term: campaign.term,
+                    content: campaign.content,
+                    creative_format: campaign.creative_format,
+                    marketing_tactic: campaign.marketing_tactic,
+                },
+                session: wit::Session {
+                    session_id: session.session_id,
+                    previous_session_id: session.previous_session_id,
+                    session_count: session.session_count,
+                    session_start: session.session_start,
+                    first_seen: session.first_seen,
+                    last_seen: session.last_seen,
+                },
+            }
+        }
+    }
+}
@@ -1,119 +1,39 @@
-use crate::exports::edgee::components::data_collection::{
-    Data, Dict, EdgeeRequest, Event, HttpMethod,
-};
-use anyhow::Context;
+use crate::exports::edgee::components::data_collection::{Dict, EdgeeRequest, Event};
 use exports::edgee::components::data_collection::Guest;
-use std::collections::HashMap;
-use woopra_payload::{WoopraPayloadIdentify, WoopraPayloadTrack};
+use woopra_core::Settings;
 
-mod woopra_payload;
+mod conversion;
 
 wit_bindgen::generate!({world: "data-collection", path: ".edgee/wit", generate_all});
 export!(Component);
 
 struct Component;
 
-const WOOPRA_HOST: &str = "https://www.woopra.com";
-const WOOPRA_TRACK_ENDPOINT: &str = "/track/ce";
-const WOOPRA_IDENTIFY_ENDPOINT: &str = "/track/identify";
-
+// the mapping lives in the host-independent woopra-core crate,
+// the component only converts the WIT types from and to its own model
 impl Guest for Component {
     fn page(edgee_event: Event, settings_dict: Dict) -> Result<EdgeeRequest, String> {
-        if let Data::Page(ref data) = edgee_event.data {
-            let settings = Settings::new(settings_dict).map_err(|e| e.to_string())?;
-
-            let mut payload =
-                WoopraPayloadTrack::new(&edgee_event, settings.project_name, "pv".to_string())
-                    .map_err(|e| e.to_string())?;
-
-            payload.add_page_properties(data);
+        let settings = Settings::new(settings_dict).map_err(|e| e.to_string())?;
 
-            let querystring = serde_qs::to_string(&payload).map_err(|e| e.to_string())?;
-
-            Ok(
-                build_edgee_request(querystring, WOOPRA_TRACK_ENDPOINT.to_string())
-                    .map_err(|e| e.to_string())?,
-            )
-        } else {
-            Err("Missing page data".to_string())
-        }
+        woopra_core::page(&edgee_event.into(), &settings)
+            .map(EdgeeRequest::from)
+            .map_err(|e| e.to_string())
     }
 
     fn track(edgee_event: Event, settings_dict: Dict) -> Result<EdgeeRequest, String> {
-        if let Data::Track(ref data) = edgee_event.data {
-            if data.name.is_empty() {
-                return Err("Track is not set".to_string());
-            }
-
-            let settings = Settings::new(settings_dict).map_err(|e| e.to_string())?;
-
-            let mut payload =
-                WoopraPayloadTrack::new(&edgee_event, settings.project_name, data.name.clone())
-                    .map_err(|e| e.to_string())?;
+        let settings = Settings::new(settings_dict).map_err(|e| e.to_string())?;
 
-            payload.add_track_properties(data);
-
-            let querystring = serde_qs::to_string(&payload).map_err(|e| e.to_string())?;
-
-            Ok(
-                build_edgee_request(querystring, WOOPRA_TRACK_ENDPOINT.to_string())
-                    .map_err(|e| e.to_string())?,
-            )
-        } else {
-            Err("Missing track data".to_string())
-        }
+        woopra_core::track(&edgee_event.into(), &settings)
+            .map(EdgeeRequest::from)
+            .map_err(|e| e.to_string())
     }
 
     fn user(edgee_event: Event, settings_dict: Dict) -> Result<EdgeeRequest, String> {
-        if let Data::User(ref data) = edgee_event.data {
-            let settings = Settings::new(settings_dict).map_err(|e| e.to_string())?;
-
-            let mut payload = WoopraPayloadIdentify::new(&edgee_event, settings.project_name)
-                .map_err(|e| e.to_string())?;
+        let settings = Settings::new(settings_dict).map_err(|e| e.to_string())?;
 
-            payload.add_user_properties(data);
-
-            let querystring = serde_qs::to_string(&payload).map_err(|e| e.to_string())?;
-
-            Ok(
-                build_edgee_request(querystring, WOOPRA_IDENTIFY_ENDPOINT.to_string())
-                    .map_err(|e| e.to_string())?,
-            )
-        } else {
-            Err("Missing user data".to_string())
-        }
-    }
-}
-
-fn build_edgee_request(querystring: String, endpoint: String) -> anyhow::Result<EdgeeRequest> {
-    let headers = vec![(String::from("content-length"), String::from("0"))];
-
-    Ok(EdgeeRequest {
-        method: HttpMethod::Get,
-        url: format!("{WOOPRA_HOST}{endpoint}?{querystring}"),
-        headers,
-        forward_client_headers: true,
-        body: String::new(),
-    })
-}
-
-pub struct Settings {
-    pub project_name: String,
-}
-
-impl Settings {
-    pub fn new(settings_dict: Dict) -> anyhow::Result<Self> {
-        let settings_map: HashMap<String, String> = settings_dict
-            .iter()
-            .map(|(key, value)| (key.to_string(), value.to_string()))
-            .collect();
-
-        let project_name = settings_map
-            .get("project_name")
-            .context("Missing example setting")?
-            .to_string();
-
-        Ok(Self { project_name })
+        woopra_core::user(&edgee_event.into(), &settings)
+            .map(EdgeeRequest::from)
+            .map_err(|e| e.to_string())
     }
 }
 
@@ -121,235 +41,10 @@ impl Settings {
 mod tests {
     use super::*;
 
-    use crate::exports::edgee::components::data_collection::{
-        Campaign, Client, Context, Data, EventType, PageData, Session, TrackData, UserData,
-    };
-    use exports::edgee::components::data_collection::Consent;
+    use crate::exports::edgee::components::data_collection::HttpMethod;
     use pretty_assertions::assert_eq;
-    use uuid::Uuid;
-
-    fn sample_user_data(edgee_id: String) -> UserData {
-        UserData {
-            user_id: "123".to_string(),
-            anonymous_id: "456".to_string(),
-            edgee_id,
-            properties: vec![
-                ("prop1".to_string(), "value1".to_string()),
-                ("prop2".to_string(), "10".to_string()),
-            ],
-        }
-    }
-
-    fn sample_user_data_without_properties(edgee_id: String) -> UserData {
-        UserData {
-            user_id: "123".to_string(),
-            anonymous_id: "456".to_string(),
-            edgee_id,
-            properties: vec![],
-        }
-    }
-
-    fn sample_user_data_with_cv_properties(edgee_id: String) -> UserData {
-        UserData {
-            user_id: "123".to_string(),
-            anonymous_id: "456".to_string(),
-            edgee_id,
-            properties: vec![
-                ("prop_name".to_string(), "value1".to_string()), // will be prefixed with cv_
-                ("cv_prop_ok".to_string(), "10".to_string()),    // won't be prefixed with cv_
-            ],
-        }
-    }
-
-    fn sample_context(edgee_id: String, locale: String, session_start: bool) -> Context {
-        Context {
-            page: sample_page_data(),
-            user: sample_user_data(edgee_id),
-            client: Client {
-                city: "Paris".to_string(),
-                ip: "192.168.0.1".to_string(),
-                locale,
-                timezone: "CET".to_string(),
-                user_agent: "Chrome".to_string(),
-                user_agent_architecture: "unknown".to_string(),
-                user_agent_bitness: "64".to_string(),
-                user_agent_full_version_list: "abc".to_string(),
-                user_agent_version_list: "abc".to_string(),
-                user_agent_mobile: "mobile".to_string(),
-                user_agent_model: "unknown".to_string(),
-                os_name: "MacOS".to_string(),
-                os_version: "latest".to_string(),
-                screen_width: 1024,
-                screen_height: 768,
-                screen_density: 2.0,
-                continent: "Europe".to_string(),
-                country_code: "FR".to_string(),
-                country_name: "France".to_string(),
-                region: "West Europe".to_string(),
-            },
-            campaign: Campaign {
-                name: "random".to_string(),
-                source: "random".to_string(),
-                medium: "random".to_string(),
-                term: "random".to_string(),
-                content: "random".to_string(),
-                creative_format: "random".to_string(),
-                marketing_tactic: "random".to_string(),
-            },
-            session: Session {
-                session_id: "random".to_string(),
-                previous_session_id: "random".to_string(),
-                session_count: 2,
-                session_start,
-                first_seen: 123,
-                last_seen: 123,
-            },
-        }
-    }
-
-    fn sample_page_data() -> PageData {
-        PageData {
-            name: "page name".to_string(),
-            category: "category".to_string(),
-            keywords: vec!["value1".to_string(), "value2".into()],
-            title: "page title".to_string(),
-            url: "https://example.com/full-url?test=1".to_string(),
-            path: "/full-path".to_string(),
-            search: "?test=1".to_string(),
-            referrer: "https://example.com/another-page".to_string(),
-            properties: vec![
-                ("prop1".to_string(), "value1".to_string()),
-                ("prop2".to_string(), "10".to_string()),
-                ("currency".to_string(), "USD".to_string()),
-            ],
-        }
-    }
-
-    fn sample_track_data(event_name: String) -> TrackData {
-        return TrackData {
-            name: event_name,
-            products: vec![],
-            properties: vec![
-                ("prop1".to_string(), "value1".to_string()),
-                ("prop2".to_string(), "10".to_string()),
-                ("currency".to_string(), "USD".to_string()),
-            ],
-        };
-    }
-
-    fn sample_track_data_without_properties(event_name: String) -> TrackData {
-        return TrackData {
-            name: event_name,
-            products: vec![],
-            properties: vec![],
-        };
-    }
-
-    fn sample_track_data_with_already_prefixed_properties(event_name: String) -> TrackData {
-        return TrackData {
-            name: event_name,
-            products: vec![],
-            properties: vec![
-                ("prop_name".to_string(), "value1".to_string()),
-                ("ce_prop_ok".to_string(), "10".to_string()),
-            ],
-        };
-    }
-
-    fn sample_page_event(
-        consent: Option<Consent>,
-        edgee_id: String,
-        locale: String,
-        session_start: bool,
-    ) -> Event {
-        Event {
-            uuid: Uuid::new_v4().to_string(),
-            timestamp: 123,
-            timestamp_millis: 123,
-            timestamp_micros: 123,
-            event_type: EventType::Page,
-            data: Data::Page(sample_page_data()),
-            context: sample_context(edgee_id, locale, session_start),
-            consent,
-        }
-    }
-
-    fn sample_track_event(
-        event_name: String,
-        consent: Option<Consent>,
-        edgee_id: String,
-        locale: String,
-        session_start: bool,
-    ) -> Event {
-        return Event {
-            uuid: Uuid::new_v4().to_string(),
-            timestamp: 123,
-            timestamp_millis: 123,
-            timestamp_micros: 123,
-            event_type: EventType::Track,
-            data: Data::Track(sample_track_data(event_name)),
-            context: sample_context(edgee_id, locale, session_start),
-            consent: consent,
-        };
-    }
-
-    fn sample_track_event_without_properties(
-        event_name: String,
-        consent: Option<Consent>,
-        edgee_id: String,
-        locale: String,
-        session_start: bool,
-    ) -> Event {
-        return Event {
-            uuid: Uuid::new_v4().to_string(),
-            timestamp: 123,
-            timestamp_millis: 123,
-            timestamp_micros: 123,
-            event_type: EventType::Track,
-            data: Data::Track(sample_track_data_without_properties(event_name)),
-            context: sample_context(edgee_id, locale, session_start),
-            consent: consent,
-        };
-    }
-
-    fn sample_track_event_with_already_prefixed_properties(
-        event_name: String,
-        consent: Option<Consent>,
-        edgee_id: String,
-        locale: String,
-        session_start: bool,
-    ) -> Event {
-        return Event {
-            uuid: Uuid::new_v4().to_string(),
-            timestamp: 123,
-            timestamp_millis: 123,
-            timestamp_micros: 123,
-            event_type: EventType::Track,
-            data: Data::Track(sample_track_data_with_already_prefixed_properties(
-                event_name,
-            )),
-            context: sample_context(edgee_id, locale, session_start),
-            consent: consent,
-        };
-    }
-
-    fn sample_user_event(
-        consent: Option<Consent>,
-        edgee_id: String,
-        locale: String,
-        session_start: bool,
-    ) -> Event {
-        return Event {
-            uuid: Uuid::new_v4().to_string(),
-            timestamp: 123,
-            timestamp_millis: 123,
-            timestamp_micros: 123,
-            event_type: EventType::User,
-            data: Data::User(sample_user_data_without_properties(edgee_id.clone())),
-            context: sample_context(edgee_id, locale, session_start),
-            consent: consent,
-        };
-    }
+    use woopra_core::data_collection::{Consent, Data};
+    use woopra_core::fixtures::*;
 
     #[test]
     fn page_works_fine() {
@@ -360,7 +55,7 @@ mod tests {
             true,
         );
         let settings = vec![("project_name".to_string(), "example.com".to_string())];
-        let result = Component::page(event, settings);
+        let result = Component::page(event.into(), settings);
 
         assert_eq!(result.is_err(), false);
         let edgee_request = result.unwrap();
@@ -384,7 +79,7 @@ mod tests {
             true,
         );
         let settings = vec![("project_name".to_string(), "example.com".to_string())];
-        let result = Component::track(event, settings);
+        let result = Component::track(event.into(), settings);
 
         assert_eq!(result.is_err(), false);
         let edgee_request = result.unwrap();
@@ -407,7 +102,7 @@ mod tests {
             true,
         );
         let settings = vec![("project_name".to_string(), "example.com".to_string())];
-        let result = Component::user(event, settings);
+        let result = Component::user(event.into(), settings);
 
         assert_eq!(result.is_err(), false);
         let edgee_request = result.unwrap();
@@ -435,7 +130,7 @@ mod tests {
         // remove the client OS version to simulate a missing property
         event.context.client.os_version = String::new();
 
-        let result = Component::track(event, settings);
+        let result = Component::track(event.into(), settings);
 
         assert_eq!(result.is_err(), false);
         let edgee_request = result.unwrap();
@@ -461,7 +156,7 @@ mod tests {
         // remove the client OS version to simulate a missing property
         event.context.client.os_name = String::new();
 
-        let result = Component::track(event, settings);
+        let result = Component::track(event.into(), settings);
 
         assert_eq!(result.is_err(), false);
         let edgee_request = result.unwrap();
@@ -487,7 +182,7 @@ mod tests {
         // remove user properties
         event.context.user.properties = vec![];
 
-        let result = Component::track(event, settings);
+        let result = Component::track(event.into(), settings);
 
         assert_eq!(result.is_err(), false);
         let edgee_request = result.unwrap();
@@ -510,7 +205,7 @@ mod tests {
         );
         let settings = vec![("project_name".to_string(), "example.com".to_string())];
 
-        let result = Component::track(event, settings);
+        let result = Component::track(event.into(), settings);
 
         assert_eq!(result.is_err(), false);
         let edgee_request = result.unwrap();
@@ -533,7 +228,7 @@ mod tests {
         );
         let settings = vec![("project_name".to_string(), "example.com".to_string())];
 
-        let result = Component::track(event, settings);
+        let result = Component::track(event.into(), settings);
 
         assert_eq!(result.is_err(), false);
         let edgee_request = result.unwrap();
@@ -560,7 +255,7 @@ mod tests {
 
         event.data = Data::User(sample_user_data_with_cv_properties("abc".to_string()));
 
-        let result = Component::user(event, settings);
+        let result = Component::user(event.into(), settings);
 
         assert_eq!(result.is_err(), false);
         let edgee_request = result.unwrap();